@@ -1,3 +1,20 @@
+use std::borrow::Borrow;
+use std::collections::TryReserveError;
+use std::iter::FusedIterator;
+use std::ops::{AddAssign, Bound, RangeBounds};
+
+pub mod multimap;
+pub mod normalized;
+pub mod set;
+pub mod stable;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use multimap::RegistOrderMultiMap;
+pub use normalized::NormalizedMap;
+pub use set::RegistOrderSet;
+pub use stable::{StableIndex, StableIndexMap};
+
 #[cfg(feature = "serde")]
 use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
 #[cfg(feature = "serde")]
@@ -5,8 +22,9 @@ use serde::ser::{Serialize, SerializeMap, Serializer};
 #[cfg(feature = "serde")]
 use std::marker::PhantomData;
 
-#[derive(Clone)]
-struct Entry<K, V> {
+#[derive(Clone, PartialEq)]
+#[repr(C)]
+struct Bucket<K, V> {
     key: K,
     val: V,
 }
@@ -15,41 +33,616 @@ struct Entry<K, V> {
 /// but it is sorted according to the key in descending order.
 /// The `RegistOrderMap` is a `HashMap` with guaranteed registration order.
 /// I have only implemented the minimum required methods, so please request them if you have any requests.
-#[derive(Clone)]
+///
+/// With the `serde` feature, the registration order is kept through serialization and
+/// deserialization as long as the format does. `serde_json` and `serde_yaml` do so out of the box;
+/// `toml` sorts table keys on deserialization unless its `preserve_order` feature is enabled.
+///
+/// Building and updating a map only requires `Eq` on the keys; values can be any type.
+///
+/// Cloning copies the entries as they are, without looking up any key, so it takes linear time.
 pub struct RegistOrderMap<K, V> {
-    entries: Vec<Entry<K, V>>,
+    entries: Vec<Bucket<K, V>>,
 }
 
 impl<K, V> RegistOrderMap<K, V> {
     /// Creates an empty RegistOrderMap.
+    ///
+    /// The map is initially created with a capacity of 0, so it will not allocate until it is first inserted into.
     pub fn new() -> Self {
         Default::default()
     }
-    fn find(&self, k: &K) -> Option<usize>
+    fn find<Q>(&self, k: &Q) -> Option<usize>
     where
-        K: Eq,
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
     {
-        self.entries.iter().position(|e| e.key == *k)
+        self.entries.iter().position(|e| e.key.borrow() == k)
     }
     /// Returns a ref2erence to the value corresponding to the key.
-    pub fn get(&self, k: &K) -> Option<&V>
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
     where
-        K: Eq,
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
     {
         match self.find(k) {
             Some(i) => Some(&self.entries[i].val),
             None => None,
         }
     }
+    /// Returns a mutable reference to the value corresponding to the key.
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.find(k).map(|i| &mut self.entries[i].val)
+    }
+    /// Returns true if the map contains the key.
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.find(k).is_some()
+    }
+    /// Returns a reference to the value corresponding to the key,
+    /// or a `MissingKey` error describing the key if it is not present.
+    pub fn try_index<Q>(&self, k: &Q) -> Result<&V, MissingKey>
+    where
+        K: Borrow<Q>,
+        Q: Eq + std::fmt::Debug + ?Sized,
+    {
+        self.get(k).ok_or_else(|| MissingKey {
+            key: format!("{:?}", k),
+        })
+    }
+    /// Returns a reference to the value corresponding to the key,
+    /// or `default` if the key is not present. The map is not modified.
+    pub fn get_or_default<'a, Q>(&'a self, k: &Q, default: &'a V) -> &'a V
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.get(k).unwrap_or(default)
+    }
+    /// Returns the index of the key in registration order, if it is present.
+    /// Like `get`, it takes any borrowed form of the key, e.g. a `&str` for `String` keys.
+    pub fn get_index_of<Q>(&self, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.find(k)
+    }
+    /// Returns the key-value pair at the given index in registration order.
+    pub fn get_index(&self, index: usize) -> Option<(&K, &V)> {
+        self.entries.get(index).map(|e| (&e.key, &e.val))
+    }
+    /// Returns the key and a mutable reference to the value at the given index in registration order.
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&K, &mut V)> {
+        self.entries.get_mut(index).map(|e| (&e.key, &mut e.val))
+    }
+    /// Returns mutable references to the values of the entries within the given index range,
+    /// in registration order. Bounds beyond the length are clamped, so an out-of-range or
+    /// empty range yields nothing instead of panicking.
+    pub fn values_mut_range<R>(&mut self, range: R) -> impl Iterator<Item = &mut V>
+    where
+        R: RangeBounds<usize>,
+    {
+        let len = self.entries.len();
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i.saturating_add(1),
+            Bound::Unbounded => 0,
+        }
+        .min(len);
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i.saturating_add(1),
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => len,
+        }
+        .clamp(start, len);
+        self.entries[start..end].iter_mut().map(|e| &mut e.val)
+    }
     /// Inserts a key-value pair into the map.
     pub fn insert(&mut self, k: K, v: V)
     where
         K: Eq,
     {
         match self.find(&k) {
-            None => self.entries.push(Entry { key: k, val: v }),
+            None => self.entries.push(Bucket { key: k, val: v }),
             Some(i) => self.entries[i].val = v,
         }
+        self.debug_check_capacity();
+    }
+    /// Inserts a key-value pair at the given index, shifting the following entries.
+    ///
+    /// If the key is new, it is inserted at `index` and `None` is returned. If it is already
+    /// present, the stored key is kept, its value is replaced and the entry is moved to `index`,
+    /// and the old value is returned.
+    ///
+    /// Panics if `index` is out of bounds: valid indices are `0..=len()` for a new key and
+    /// `0..len()` for an existing one.
+    pub fn shift_insert(&mut self, index: usize, k: K, v: V) -> Option<V>
+    where
+        K: Eq,
+    {
+        match self.find(&k) {
+            Some(i) => {
+                assert!(
+                    index < self.entries.len(),
+                    "index out of bounds: the len is {} but the index is {}",
+                    self.entries.len(),
+                    index
+                );
+                let old = std::mem::replace(&mut self.entries[i].val, v);
+                self.move_index(i, index);
+                self.debug_check_capacity();
+                Some(old)
+            }
+            None => {
+                self.entries.insert(index, Bucket { key: k, val: v });
+                self.debug_check_capacity();
+                None
+            }
+        }
+    }
+    /// Inserts the key-value pair if the key is not present, and returns a mutable reference to the
+    /// value in the map, together with true if it was inserted. If the key was already present,
+    /// `v` is dropped and the existing value is returned along with false.
+    pub fn insert_or_get(&mut self, k: K, v: V) -> (&mut V, bool)
+    where
+        K: Eq,
+    {
+        match self.entry(k) {
+            Entry::Occupied(entry) => (entry.into_mut(), false),
+            Entry::Vacant(entry) => (entry.insert(v), true),
+        }
+    }
+    /// Inserts the key-value pairs whose keys are not yet present, appending them in iteration order.
+    ///
+    /// Unlike `insert` and `extend`, which overwrite the values of existing keys, existing keys are
+    /// skipped entirely: their values and positions are kept and the new values are dropped.
+    /// This makes it suitable for filling in defaults.
+    pub fn insert_many<I>(&mut self, iter: I)
+    where
+        K: Eq,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        for (k, v) in iter {
+            if self.find(&k).is_none() {
+                self.entries.push(Bucket { key: k, val: v });
+            }
+        }
+        self.debug_check_capacity();
+    }
+    /// Like `insert_many`, but only takes keys: the missing ones are appended in iteration order
+    /// with the value computed by `default`, which is not called for keys already present.
+    pub fn ensure_keys<I, F>(&mut self, keys: I, mut default: F)
+    where
+        K: Eq,
+        I: IntoIterator<Item = K>,
+        F: FnMut(&K) -> V,
+    {
+        for k in keys {
+            if self.find(&k).is_none() {
+                let v = default(&k);
+                self.entries.push(Bucket { key: k, val: v });
+            }
+        }
+        self.debug_check_capacity();
+    }
+    /// Replaces the value for the key with `new` only if the current value equals `expected`.
+    ///
+    /// Returns `Ok(())` on success. Otherwise the map is unchanged, `new` is dropped and
+    /// a clone of the current value is returned as `Err(Some(_))`, or `Err(None)` if the key is not
+    /// present. The clone leaves the map free to be updated again, e.g. to retry with a new value.
+    pub fn replace_if_eq<Q>(&mut self, k: &Q, expected: &V, new: V) -> Result<(), Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        V: PartialEq + Clone,
+    {
+        match self.find(k) {
+            Some(i) if self.entries[i].val == *expected => {
+                self.entries[i].val = new;
+                Ok(())
+            }
+            Some(i) => Err(Some(self.entries[i].val.clone())),
+            None => Err(None),
+        }
+    }
+    /// Inserts or overwrites each key-value pair like `insert`, and returns the previous values
+    /// in the order of the input, `None` for keys that were newly inserted.
+    pub fn upsert_many<I>(&mut self, iter: I) -> Vec<Option<V>>
+    where
+        K: Eq,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        iter.into_iter()
+            .map(|(k, v)| match self.find(&k) {
+                Some(i) => Some(std::mem::replace(&mut self.entries[i].val, v)),
+                None => {
+                    self.entries.push(Bucket { key: k, val: v });
+                    self.debug_check_capacity();
+                    None
+                }
+            })
+            .collect()
+    }
+    /// Returns the index and a mutable reference to the value for the key,
+    /// inserting the result of `f` at the end of the map first if the key is not present.
+    pub fn get_full_or_insert_with<F>(&mut self, k: K, f: F) -> (usize, &mut V)
+    where
+        K: Eq,
+        F: FnOnce() -> V,
+    {
+        let index = match self.find(&k) {
+            Some(i) => i,
+            None => {
+                self.entries.push(Bucket { key: k, val: f() });
+                self.debug_check_capacity();
+                self.entries.len() - 1
+            }
+        };
+        (index, &mut self.entries[index].val)
+    }
+    /// Gets the entry for a borrowed key. Unlike `entry`, the owned key is only created,
+    /// with `ToOwned::to_owned`, when a value is inserted into a vacant entry.
+    pub fn entry_ref<'a, 'q, Q>(&'a mut self, k: &'q Q) -> EntryRef<'a, 'q, K, Q, V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        match self.find(k) {
+            Some(index) => EntryRef::Occupied(OccupiedEntry { map: self, index }),
+            None => EntryRef::Vacant(VacantEntryRef { map: self, key: k }),
+        }
+    }
+    /// Returns a mutable reference to the value for the key,
+    /// inserting `V::default()` at the end of the map first if the key is not present.
+    pub fn get_or_insert_default(&mut self, k: K) -> &mut V
+    where
+        K: Eq,
+        V: Default,
+    {
+        self.entry(k).or_default()
+    }
+    /// Looks up a borrowed key and returns a mutable reference to its value. If the key is not
+    /// present, `make` is called with it to build the owned key and the value, which are appended
+    /// to the end of the map, so no owned key is created on a hit.
+    ///
+    /// Panics if the key built by `make` is not equal to `k`, as it could duplicate another key.
+    pub fn get_or_insert_ref<Q, F>(&mut self, k: &Q, make: F) -> &mut V
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        F: FnOnce(&Q) -> (K, V),
+    {
+        if let Some(i) = self.find(k) {
+            return &mut self.entries[i].val;
+        }
+        let (key, val) = make(k);
+        assert!(
+            key.borrow() == k,
+            "the key built by `make` does not match the looked up key"
+        );
+        self.entries.push(Bucket { key, val });
+        self.debug_check_capacity();
+        &mut self.entries.last_mut().unwrap().val
+    }
+    /// Adds one to the counter for the key, starting from `V::default()` at the end of the map
+    /// if the key is not present.
+    pub fn increment(&mut self, k: K)
+    where
+        K: Eq,
+        V: Default + AddAssign + From<u8>,
+    {
+        *self.entry(k).or_default() += V::from(1);
+    }
+    /// Removes a key from the map, returning the value at the key if the key was previously in the map.
+    ///
+    /// The following entries are shifted down, so the registration order of the rest is kept.
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.find(k).map(|i| self.remove_index(i).val)
+    }
+    /// Removes a key from the map, returning the former index, the stored key and the value
+    /// if the key was previously in the map. The following entries are shifted down.
+    pub fn remove_full<Q>(&mut self, k: &Q) -> Option<(usize, K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.find(k).map(|i| {
+            let e = self.remove_index(i);
+            (i, e.key, e.val)
+        })
+    }
+    /// Removes a key from the map only if the predicate returns true for its value,
+    /// returning the removed value. The order of the rest is kept.
+    pub fn remove_if<Q, F>(&mut self, k: &Q, pred: F) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        F: FnOnce(&V) -> bool,
+    {
+        match self.find(k) {
+            Some(i) if pred(&self.entries[i].val) => Some(self.remove_index(i).val),
+            _ => None,
+        }
+    }
+    /// Removes the first entry in registration order and returns it, or `None` if the map is empty.
+    pub fn pop_front(&mut self) -> Option<(K, V)> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let e = self.remove_index(0);
+        Some((e.key, e.val))
+    }
+    /// Removes the last entry in registration order and returns it, or `None` if the map is empty.
+    pub fn pop(&mut self) -> Option<(K, V)> {
+        let e = self.entries.pop()?;
+        self.shrink_if_sparse();
+        Some((e.key, e.val))
+    }
+    /// Removes the first entry in registration order and returns it, like `pop_front`.
+    #[inline]
+    pub fn remove_first(&mut self) -> Option<(K, V)> {
+        self.pop_front()
+    }
+    /// Removes the last entry in registration order and returns it, like `pop`.
+    #[inline]
+    pub fn remove_last(&mut self) -> Option<(K, V)> {
+        self.pop()
+    }
+    /// Keeps the first `len` entries in registration order and drops the rest.
+    /// Has no effect if `len` is not less than the current length. The capacity is kept.
+    pub fn truncate(&mut self, len: usize) {
+        self.entries.truncate(len);
+        self.debug_check_capacity();
+    }
+    /// Removes the entries in the given range of positions and returns them in registration order,
+    /// keeping the order of the rest.
+    ///
+    /// Panics if the range is out of bounds or its start is greater than its end, like `Vec::drain`.
+    pub fn remove_range<R>(&mut self, range: R) -> Vec<(K, V)>
+    where
+        R: RangeBounds<usize>,
+    {
+        let removed = self.entries.drain(range).map(|e| (e.key, e.val)).collect();
+        self.shrink_if_sparse();
+        removed
+    }
+    fn remove_index(&mut self, index: usize) -> Bucket<K, V> {
+        let e = self.entries.remove(index);
+        self.shrink_if_sparse();
+        e
+    }
+    // With the `auto-shrink` feature, the removing methods give memory back once less than
+    // a quarter of the capacity is in use: `remove`, `remove_full`, `remove_if`, `pop_front`,
+    // `pop`, `remove_range`, `OccupiedEntry::remove_full`, `OccupiedEntry::replace_entry_with`
+    // when it removes the entry, and `OccupiedEntry::remove_and_reinsert` when it removes another
+    // entry. Bulk operations such as `retain`, `try_retain`, `drain_filter_ordered`, `truncate`
+    // and `clear` keep the capacity.
+    fn shrink_if_sparse(&mut self) {
+        #[cfg(feature = "auto-shrink")]
+        if self.entries.len() * 4 < self.entries.capacity() {
+            self.entries.shrink_to_fit();
+        }
+        self.debug_check_capacity();
+    }
+    // Catches bookkeeping bugs in debug builds after operations that grow or shrink the storage,
+    // should it ever stop being a plain `Vec`.
+    #[inline]
+    fn debug_check_capacity(&self) {
+        debug_assert!(
+            self.len() <= self.capacity(),
+            "len {} exceeds capacity {}",
+            self.len(),
+            self.capacity()
+        );
+    }
+    /// Gets the given key's corresponding entry in the map for in-place manipulation.
+    pub fn entry(&mut self, k: K) -> Entry<'_, K, V>
+    where
+        K: Eq,
+    {
+        match self.find(&k) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, key: k }),
+        }
+    }
+    /// Moves the entry at index `from` to index `to`, shifting the entries in between.
+    ///
+    /// Panics if `from` or `to` is out of bounds.
+    pub fn move_index(&mut self, from: usize, to: usize) {
+        if from < to {
+            self.entries[from..=to].rotate_left(1);
+        } else if to < from {
+            self.entries[to..=from].rotate_right(1);
+        }
+    }
+    /// Moves the entry for the key to the front of the registration order.
+    /// Returns false if the key is not present.
+    pub fn move_to_front<Q>(&mut self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match self.find(k) {
+            Some(i) => {
+                self.move_index(i, 0);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Moves the entry for the key to the back of the registration order.
+    /// Returns false if the key is not present.
+    pub fn move_to_back<Q>(&mut self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        match self.find(k) {
+            Some(i) => {
+                self.move_index(i, self.len() - 1);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Retains only the elements specified by the predicate, keeping the order of the rest.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        self.entries.retain_mut(|e| f(&e.key, &mut e.val));
+        self.debug_check_capacity();
+    }
+    /// Like `retain`, but the predicate may fail, which stops the pass at the first `Err`.
+    ///
+    /// When an `Err` is returned, the entries before the failing one have been kept or removed
+    /// according to the predicate, the failing entry is kept (including any changes the predicate
+    /// made to its value) and the entries after it are kept without the predicate being called.
+    /// The order of the kept entries is preserved in every case, even if the predicate panics.
+    pub fn try_retain<F, E>(&mut self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&K, &mut V) -> Result<bool, E>,
+    {
+        let mut result = Ok(());
+        self.entries.retain_mut(|e| match result {
+            Ok(()) => f(&e.key, &mut e.val).unwrap_or_else(|err| {
+                result = Err(err);
+                true
+            }),
+            Err(_) => true,
+        });
+        self.debug_check_capacity();
+        result
+    }
+    /// Like `retain`, but returns the number of removed elements.
+    pub fn retain_count<F>(&mut self, f: F) -> usize
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let len = self.len();
+        self.retain(f);
+        len - self.len()
+    }
+    /// Like `retain`, but the predicate also gets the position each entry had before the pass.
+    pub fn retain_indexed<F>(&mut self, mut f: F)
+    where
+        F: FnMut(usize, &K, &mut V) -> bool,
+    {
+        let mut index = 0;
+        self.retain(|k, v| {
+            let keep = f(index, k, v);
+            index += 1;
+            keep
+        });
+    }
+    /// Consumes the map and returns it with only the entries specified by the predicate,
+    /// in registration order. Like `retain`, it reuses the storage of the map.
+    pub fn into_retained<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.retain(|k, v| f(k, v));
+        self
+    }
+    /// Inverts the map, grouping the keys by their values.
+    ///
+    /// The values appear in the order of their first occurrence, and the keys of each group
+    /// in registration order.
+    pub fn group_by_value(&self) -> RegistOrderMap<V, Vec<&K>>
+    where
+        V: Eq + Clone,
+    {
+        let mut groups = RegistOrderMap::new();
+        for e in self.entries.iter() {
+            groups
+                .entry(e.val.clone())
+                .or_insert_with(Vec::new)
+                .push(&e.key);
+        }
+        groups
+    }
+    /// Sorts the map by its keys. The sort is stable.
+    pub fn sort_keys(&mut self)
+    where
+        K: Ord,
+    {
+        self.entries.sort_by(|a, b| a.key.cmp(&b.key));
+    }
+    /// Sorts the map with a comparison function over key-value pairs. The sort is stable.
+    pub fn sort_by<F>(&mut self, mut cmp: F)
+    where
+        F: FnMut(&K, &V, &K, &V) -> std::cmp::Ordering,
+    {
+        self.entries
+            .sort_by(|a, b| cmp(&a.key, &a.val, &b.key, &b.val));
+    }
+    /// Returns true if both maps hold the same key-value pairs, regardless of registration order.
+    /// Unlike `==`, which also compares the order.
+    pub fn eq_unordered(&self, other: &Self) -> bool
+    where
+        K: Eq,
+        V: PartialEq,
+    {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+    /// Returns true if the entries are already sorted by the given key extraction function,
+    /// e.g. to skip an unnecessary `sort_by`.
+    pub fn is_sorted_by_key<B, F>(&self, mut f: F) -> bool
+    where
+        B: Ord,
+        F: FnMut(&K, &V) -> B,
+    {
+        self.entries.is_sorted_by_key(|e| f(&e.key, &e.val))
+    }
+    /// Binary searches the map with a key extraction function.
+    ///
+    /// Returns `Ok` with the index of a matching entry, or `Err` with the index
+    /// where a matching entry could be inserted while keeping the order.
+    /// The result is only meaningful if the map is sorted by the same key function,
+    /// e.g. after `sort_keys` or `sort_by`.
+    pub fn binary_search_by_key<B, F>(&self, b: &B, mut f: F) -> Result<usize, usize>
+    where
+        B: Ord,
+        F: FnMut(&K, &V) -> B,
+    {
+        self.entries.binary_search_by_key(b, |e| f(&e.key, &e.val))
+    }
+    /// Creates an iterator which removes and yields the entries for which the predicate returns true.
+    ///
+    /// The removed entries are yielded in registration order and the remaining entries keep their
+    /// order. The map is processed in a single pass as the iterator advances. If the iterator is
+    /// dropped before it is exhausted, or the predicate panics, the entries not yet visited are
+    /// kept. The kept entries are compacted in place, so no memory is allocated.
+    pub fn drain_filter_ordered<'a, F>(
+        &'a mut self,
+        mut pred: F,
+    ) -> impl Iterator<Item = (K, V)> + 'a
+    where
+        F: FnMut(&K, &mut V) -> bool + 'a,
+    {
+        self.entries
+            .extract_if(.., move |e| pred(&e.key, &mut e.val))
+            .map(|e| (e.key, e.val))
+    }
+    /// Clears the map, removing all key-value pairs. Keeps the allocated memory for reuse.
+    ///
+    /// This is the single point where all internal state is reset; any state kept alongside the
+    /// entries must be cleared here as well.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.debug_check_capacity();
     }
     /// Returns true if the map contains no elements.
     #[inline]
@@ -63,11 +656,121 @@ impl<K, V> RegistOrderMap<K, V> {
             inner: self.entries.iter(),
         }
     }
+    /// Returns an iterator over `size` entries of the map at a time, in registration order.
+    /// The last chunk may be shorter. Each chunk is itself an iterator over `(&'a K, &'a V)`.
+    ///
+    /// Panics if `size` is 0.
+    #[inline]
+    pub fn chunks(&self, size: usize) -> Chunks<'_, K, V> {
+        Chunks {
+            inner: self.entries.chunks(size),
+        }
+    }
+    /// Returns an iterator over all overlapping runs of `size` consecutive entries, in registration order.
+    /// Each window is itself an iterator over `(&'a K, &'a V)`.
+    ///
+    /// Panics if `size` is 0.
+    #[inline]
+    pub fn windows(&self, size: usize) -> Windows<'_, K, V> {
+        Windows {
+            inner: self.entries.windows(size),
+        }
+    }
+    /// An iterator visiting all key-value pairs in registration order together with their index,
+    /// which is the registration position. The iterator element type is `(usize, &'a K, &'a V)`.
+    pub fn indexed_iter(&self) -> impl Iterator<Item = (usize, &K, &V)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (i, &e.key, &e.val))
+    }
+    /// An iterator visiting all key-value pairs in reverse registration order, newest first.
+    pub fn rev_iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().rev().map(|e| (&e.key, &e.val))
+    }
+    /// An iterator visiting all values in registration order.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|e| &e.val)
+    }
+    /// Applies `f` to the entries in registration order and returns the first `Some` it produces.
+    pub fn find_map<B, F>(&self, mut f: F) -> Option<B>
+    where
+        F: FnMut(&K, &V) -> Option<B>,
+    {
+        self.entries.iter().find_map(|e| f(&e.key, &e.val))
+    }
+    /// Sums the values in registration order, into any type that can sum up `&V`.
+    pub fn sum_values<'a, S>(&'a self) -> S
+    where
+        S: std::iter::Sum<&'a V>,
+    {
+        self.values().sum()
+    }
+    /// Returns the greatest value, or `None` if the map is empty.
+    /// Of several equally great values, the last one in registration order is returned,
+    /// like `Iterator::max`.
+    pub fn max_value(&self) -> Option<&V>
+    where
+        V: Ord,
+    {
+        self.values().max()
+    }
     /// Returns the number of elements in the map.
     #[inline]
     pub fn len(&self) -> usize {
         self.entries.len()
     }
+    /// Creates a consuming iterator visiting all the keys in registration order.
+    pub fn into_keys(self) -> IntoKeys<K, V> {
+        IntoKeys {
+            inner: self.entries.into_iter(),
+        }
+    }
+    /// Creates a consuming iterator visiting all the values in registration order.
+    pub fn into_values(self) -> IntoValues<K, V> {
+        IntoValues {
+            inner: self.entries.into_iter(),
+        }
+    }
+    /// Returns the entries as a slice of key-value pairs in registration order, without copying.
+    ///
+    /// This reinterprets the internal storage in place, which requires the internal entry type to
+    /// have the same layout as `(K, V)`. Rust does not guarantee that in general, so it is checked
+    /// at compile time for the concrete `K` and `V`: should the layouts ever differ, a call to this
+    /// method fails to compile rather than reading memory incorrectly.
+    pub fn as_pairs(&self) -> &[(K, V)] {
+        Bucket::as_pairs(&self.entries)
+    }
+    /// Clones the map into storage allocated for exactly `len()` entries, leaving out any spare
+    /// capacity of `self`. The allocator may still provide more than requested.
+    pub fn clone_compact(&self) -> Self
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut entries = Vec::new();
+        entries.reserve_exact(self.entries.len());
+        entries.extend(self.entries.iter().cloned());
+        Self { entries }
+    }
+    /// An iterator visiting all entries as `&(K, V)` in registration order, for code written
+    /// against `Vec<(K, V)>`. It is built on `as_pairs` and shares its compile-time layout check.
+    pub fn pairs(&self) -> impl Iterator<Item = &(K, V)> {
+        self.as_pairs().iter()
+    }
+    /// Replaces the contents of `target` with a clone of `self`, reusing the storage of `target`
+    /// so that no allocation is needed when its capacity suffices. Useful for recycling maps.
+    pub fn clone_into(&self, target: &mut Self)
+    where
+        K: Clone,
+        V: Clone,
+    {
+        target.clone_from(self);
+    }
+    /// Consumes the map and returns its key-value pairs in registration order.
+    pub fn into_pairs(self) -> Vec<(K, V)> {
+        self.entries.into_iter().map(|e| (e.key, e.val)).collect()
+    }
     /// Creates an empty `RegistOrderMap` with at least the specified capacity.
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self {
@@ -75,63 +778,745 @@ impl<K, V> RegistOrderMap<K, V> {
             entries: Vec::with_capacity(capacity),
         }
     }
+    /// Creates a map from a `HashMap`, registered in key order, so that the result, and its
+    /// serialized output, do not depend on the iteration order of the `HashMap`.
+    pub fn from_hash_map_sorted<S>(map: std::collections::HashMap<K, V, S>) -> Self
+    where
+        K: Ord,
+    {
+        let mut entries: Vec<_> = map
+            .into_iter()
+            .map(|(key, val)| Bucket { key, val })
+            .collect();
+        // The keys are unique, so an unstable sort gives the same order.
+        entries.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+        Self { entries }
+    }
+    /// Creates an empty `RegistOrderMap` for exactly `capacity` elements, without the extra room
+    /// amortized growth would leave. The allocator may still provide more than requested.
+    pub fn with_exact_capacity(capacity: usize) -> Self {
+        let mut map = Self::new();
+        map.reserve_exact(capacity);
+        map
+    }
+    /// Returns the number of elements the map can hold without reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+    // Lets tests check that an operation reused the allocation instead of replacing it.
+    #[cfg(test)]
+    fn backing_ptr(&self) -> *const u8 {
+        self.entries.as_ptr().cast()
+    }
+    /// Reserves capacity for at least `additional` more elements.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.entries.reserve(additional);
+        self.debug_check_capacity();
+    }
+    /// Reserves the minimum capacity for at least `additional` more elements,
+    /// without deliberately over-allocating like `reserve` does.
+    #[inline]
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.entries.reserve_exact(additional);
+        self.debug_check_capacity();
+    }
+    /// Shrinks the capacity of the map with a lower bound.
+    /// The capacity will remain at least as large as both the length and `min_capacity`.
+    #[inline]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.entries.shrink_to(min_capacity);
+        self.debug_check_capacity();
+    }
+    /// Tries to reserve capacity for at least `additional` more elements.
+    /// Returns an error instead of panicking if the allocation fails.
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.entries.try_reserve(additional)?;
+        self.debug_check_capacity();
+        Ok(())
+    }
 }
 
-impl<K, V> Default for RegistOrderMap<K, V> {
-    fn default() -> Self {
-        Self {
-            entries: Vec::new(),
-        }
-    }
+/// The error returned by `RegistOrderMap::try_index` when the key is not present.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissingKey {
+    key: String,
 }
 
-impl<K, V, const N: usize> From<[(K, V); N]> for RegistOrderMap<K, V>
-where
-    K: Eq + Copy,
-    V: Copy,
-{
-    fn from(arr: [(K, V); N]) -> Self {
-        Self {
-            entries: arr.iter().map(|e| Entry { key: e.0, val: e.1 }).collect(),
-        }
+impl MissingKey {
+    /// Returns the `Debug` representation of the missing key.
+    pub fn key(&self) -> &str {
+        &self.key
     }
 }
 
-impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for Entry<K, V> {
+impl std::fmt::Display for MissingKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Entry")
-            .field("key", &self.key)
-            .field("val", &self.val)
-            .finish()
+        write!(f, "key not found: {}", self.key)
     }
 }
 
-impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for RegistOrderMap<K, V> {
+impl std::error::Error for MissingKey {}
+
+/// The error returned by the `TryFrom<Vec<(K, V)>>` conversion when a key occurs more than once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateKey {
+    index: usize,
+}
+
+impl DuplicateKey {
+    /// Returns the index in the input of the first pair whose key was already seen.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl std::fmt::Display for DuplicateKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_list()
-            .entries(self.entries.iter())
-            .finish()
+        write!(f, "duplicate key at index {}", self.index)
     }
 }
 
-pub struct Iter<'a, K: 'a, V: 'a> {
-    inner: std::slice::Iter<'a, Entry<K, V>>,
+impl std::error::Error for DuplicateKey {}
+
+impl<K: Clone, V: Clone> Clone for RegistOrderMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+    /// Reuses the storage of `self`, like `Vec::clone_from`.
+    fn clone_from(&mut self, source: &Self) {
+        self.entries.clone_from(&source.entries);
+    }
 }
 
-impl<'a, K: 'a, V: 'a> Iterator for Iter<'a, K, V>
+/// Two maps are equal if they hold the same key-value pairs in the same registration order.
+impl<K, V> PartialEq for RegistOrderMap<K, V>
 where
-    K: Eq,
+    K: PartialEq,
+    V: PartialEq,
 {
-    type Item = (&'a K, &'a V);
+    fn eq(&self, other: &Self) -> bool {
+        // Maps of different lengths are unequal without comparing any entry.
+        self.len() == other.len() && self.entries == other.entries
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.inner.next() {
-            None => None,
-            Some(entry) => Some((&entry.key, &entry.val)),
+impl<K: Eq, V: Eq> Eq for RegistOrderMap<K, V> {}
+
+impl<K, V> Default for RegistOrderMap<K, V> {
+    /// Creates an empty `RegistOrderMap`, which does not allocate, like `new`.
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
         }
     }
 }
 
+impl<K, V, const N: usize> From<[(K, V); N]> for RegistOrderMap<K, V>
+where
+    K: Eq,
+{
+    /// Creates a map from an array of pairs like `insert` would,
+    /// so a duplicate key keeps its first position and takes the last value.
+    fn from(arr: [(K, V); N]) -> Self {
+        let mut map = Self::with_capacity(N);
+        map.extend(arr);
+        map
+    }
+}
+
+impl<K, V> From<std::collections::BTreeMap<K, V>> for RegistOrderMap<K, V> {
+    /// Creates a map registered in the key order of the `BTreeMap`.
+    /// The keys are already unique, so no lookups are made.
+    fn from(map: std::collections::BTreeMap<K, V>) -> Self {
+        Self {
+            entries: map
+                .into_iter()
+                .map(|(key, val)| Bucket { key, val })
+                .collect(),
+        }
+    }
+}
+
+impl<K, V, S> From<std::collections::HashMap<K, V, S>> for RegistOrderMap<K, V> {
+    /// Creates a map registered in the iteration order of the `HashMap`, which is arbitrary
+    /// and differs between runs, and so does the serialized output of the map.
+    /// Use `RegistOrderMap::from_hash_map_sorted` for a deterministic order.
+    /// The keys are already unique, so no lookups are made.
+    fn from(map: std::collections::HashMap<K, V, S>) -> Self {
+        Self {
+            entries: map
+                .into_iter()
+                .map(|(key, val)| Bucket { key, val })
+                .collect(),
+        }
+    }
+}
+
+impl<K, V> TryFrom<Vec<(K, V)>> for RegistOrderMap<K, V>
+where
+    K: Eq,
+{
+    type Error = DuplicateKey;
+
+    /// Creates a map from the pairs in order, failing on the first key that occurs twice
+    /// instead of overwriting it like `From` and `collect` do.
+    fn try_from(pairs: Vec<(K, V)>) -> Result<Self, Self::Error> {
+        let mut map = Self::with_capacity(pairs.len());
+        for (index, (k, v)) in pairs.into_iter().enumerate() {
+            if map.find(&k).is_some() {
+                return Err(DuplicateKey { index });
+            }
+            map.entries.push(Bucket { key: k, val: v });
+        }
+        Ok(map)
+    }
+}
+
+impl<K, V> Bucket<K, V> {
+    // `Bucket` is `repr(C)`, so its fields stay in declaration order. Tuples are `repr(Rust)` and
+    // their layout is not guaranteed, although pairs are laid out the same way in practice
+    // (a plain `repr(Rust)` struct is not: it may swap the fields). Hence the check for every
+    // `K` and `V` actually used by `as_pairs`.
+    const SAME_LAYOUT_AS_PAIR: () = assert!(
+        std::mem::size_of::<Self>() == std::mem::size_of::<(K, V)>()
+            && std::mem::align_of::<Self>() == std::mem::align_of::<(K, V)>()
+            && std::mem::offset_of!(Self, key) == std::mem::offset_of!((K, V), 0)
+            && std::mem::offset_of!(Self, val) == std::mem::offset_of!((K, V), 1),
+        "Bucket<K, V> and (K, V) have different layouts"
+    );
+
+    fn as_pairs(buckets: &[Self]) -> &[(K, V)] {
+        let () = Self::SAME_LAYOUT_AS_PAIR;
+        // SAFETY: `SAME_LAYOUT_AS_PAIR` is evaluated at compile time and ensures that `Bucket<K, V>`
+        // and `(K, V)` have the same size, alignment and field offsets, so a slice of one is a valid
+        // slice of the other with the same length. The lifetime is carried over from `buckets`.
+        unsafe { std::slice::from_raw_parts(buckets.as_ptr().cast::<(K, V)>(), buckets.len()) }
+    }
+}
+
+impl<K, V> Extend<(K, V)> for RegistOrderMap<K, V>
+where
+    K: Eq,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K, V> Extend<(&'a K, &'a V)> for RegistOrderMap<K, V>
+where
+    K: Eq + Copy,
+    V: Copy,
+{
+    fn extend<I: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().map(|(k, v)| (*k, *v)));
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for RegistOrderMap<K, V>
+where
+    K: Eq,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = RegistOrderMap::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for Bucket<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Entry")
+            .field("key", &self.key)
+            .field("val", &self.val)
+            .finish()
+    }
+}
+
+// Formats an entry as `index => key: val`, for the alternate `Debug` output of the map.
+struct Indexed<'a, K, V>(usize, &'a Bucket<K, V>);
+
+impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for Indexed<'_, K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} => ", self.0)?;
+        self.1.key.fmt(f)?;
+        f.write_str(": ")?;
+        self.1.val.fmt(f)
+    }
+}
+
+/// `{:?}` lists the entries in registration order. `{:#?}` lists one entry per line,
+/// prefixed by its index, e.g. `0 => "key2": 20`.
+impl<K: std::fmt::Debug, V: std::fmt::Debug> std::fmt::Debug for RegistOrderMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            let entries = self.entries.iter().enumerate();
+            return f
+                .debug_list()
+                .entries(entries.map(|(i, e)| Indexed(i, e)))
+                .finish();
+        }
+        f.debug_list().entries(self.entries.iter()).finish()
+    }
+}
+
+pub struct Iter<'a, K: 'a, V: 'a> {
+    inner: std::slice::Iter<'a, Bucket<K, V>>,
+}
+
+impl<'a, K: 'a, V: 'a> Iter<'a, K, V> {
+    /// Returns the entries not yet yielded as a slice of key-value pairs, without consuming them.
+    /// See `RegistOrderMap::as_pairs` for the layout requirement.
+    pub fn as_slice(&self) -> &'a [(K, V)] {
+        Bucket::as_pairs(self.inner.as_slice())
+    }
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for Iter<'a, K, V>
+where
+    K: Eq,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner.next() {
+            None => None,
+            Some(entry) => Some((&entry.key, &entry.val)),
+        }
+    }
+}
+
+impl<K, V> IntoIterator for RegistOrderMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Creates a consuming iterator visiting all key-value pairs in registration order.
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            inner: self.entries.into_iter(),
+        }
+    }
+}
+
+pub struct IntoIter<K, V> {
+    inner: std::vec::IntoIter<Bucket<K, V>>,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|e| (e.key, e.val))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoIter<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|e| (e.key, e.val))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K, V> FusedIterator for IntoIter<K, V> {}
+
+pub struct IntoKeys<K, V> {
+    inner: std::vec::IntoIter<Bucket<K, V>>,
+}
+
+impl<K, V> Iterator for IntoKeys<K, V> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|e| e.key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoKeys<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|e| e.key)
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoKeys<K, V> {}
+
+impl<K, V> FusedIterator for IntoKeys<K, V> {}
+
+pub struct IntoValues<K, V> {
+    inner: std::vec::IntoIter<Bucket<K, V>>,
+}
+
+impl<K, V> Iterator for IntoValues<K, V> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|e| e.val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IntoValues<K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|e| e.val)
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoValues<K, V> {}
+
+impl<K, V> FusedIterator for IntoValues<K, V> {}
+
+/// A view into a single entry in a map, which may either be vacant or occupied.
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K, V> Entry<'a, K, V> {
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+    /// Ensures a value is in the entry by inserting the default if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+    /// Ensures a value is in the entry by inserting the result of the default function if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+    /// Like `or_insert_with`, but the default function may fail.
+    /// On `Err`, nothing is inserted and the error is returned.
+    pub fn or_insert_with_result<F, E>(self, default: F) -> Result<&'a mut V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(default()?)),
+        }
+    }
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+/// A view into an occupied entry in a `RegistOrderMap`. It is part of the `Entry` enum.
+pub struct OccupiedEntry<'a, K, V> {
+    map: &'a mut RegistOrderMap<K, V>,
+    index: usize,
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    /// Gets a reference to the key stored in the map.
+    pub fn key(&self) -> &K {
+        &self.map.entries[self.index].key
+    }
+    /// Gets a mutable reference to the key stored in the map, for the rare edits that don't
+    /// change how the key compares, such as a field ignored by its `Eq`.
+    ///
+    /// A key changed into one equal to another key leaves the map with a duplicate, and lookups
+    /// will then only find the first of them. Requires the `key-mut` feature.
+    #[cfg(feature = "key-mut")]
+    pub fn key_mut(&mut self) -> &mut K {
+        &mut self.map.entries[self.index].key
+    }
+    /// Returns the index of the entry in registration order.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        &self.map.entries[self.index].val
+    }
+    /// Gets a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map.entries[self.index].val
+    }
+    /// Converts the entry into a mutable reference to its value.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map.entries[self.index].val
+    }
+    /// Removes the entry from the map, shifting the following entries, and returns its former
+    /// index with the owned key and value, e.g. to put it back later with `shift_insert`.
+    pub fn remove_full(self) -> (usize, K, V) {
+        let e = self.map.remove_index(self.index);
+        (self.index, e.key, e.val)
+    }
+    /// Sets the value of the entry, and returns the entry's old value.
+    pub fn insert(&mut self, v: V) -> V {
+        std::mem::replace(self.get_mut(), v)
+    }
+    /// Provides the key and the owned value to `f`. If it returns `Some`, the new value is stored
+    /// at the same position and the entry stays occupied; if it returns `None`, the entry is
+    /// removed, shifting the following entries, and a vacant entry for the key is returned.
+    ///
+    /// If `f` panics, the entry is removed as if it had returned `None`, and its key is dropped.
+    pub fn replace_entry_with<F>(self, f: F) -> Entry<'a, K, V>
+    where
+        F: FnOnce(&K, V) -> Option<V>,
+    {
+        // The value is moved out of its bucket in place, so keeping the entry shifts nothing.
+        let mut hole = Hole {
+            entries: &mut self.map.entries,
+            index: self.index,
+        };
+        // SAFETY: the value is read only once, and until it is written back `hole` removes its
+        // bucket without dropping it, both on the `None` path and when `f` panics.
+        let val = unsafe { std::ptr::read(&hole.entries[hole.index].val) };
+        match f(&hole.entries[hole.index].key, val) {
+            Some(val) => {
+                // SAFETY: the bucket's value was moved out above, so it is overwritten without a drop.
+                unsafe { std::ptr::write(&mut hole.entries[hole.index].val, val) };
+                std::mem::forget(hole);
+                self.map.debug_check_capacity();
+                Entry::Occupied(self)
+            }
+            None => {
+                let key = hole.take_key();
+                std::mem::forget(hole);
+                self.map.shrink_if_sparse();
+                Entry::Vacant(VacantEntry { map: self.map, key })
+            }
+        }
+    }
+    /// Replaces the key of the entry with `new_key`, keeping its value and its position,
+    /// and returns a mutable reference to the value.
+    ///
+    /// If `new_key` is already used by another entry, that entry is removed and its value dropped,
+    /// shifting the following entries; the re-keyed entry keeps its place relative to the rest.
+    pub fn remove_and_reinsert(self, new_key: K) -> &'a mut V
+    where
+        K: Eq,
+    {
+        let mut index = self.index;
+        if let Some(other) = self.map.find(&new_key) {
+            if other != index {
+                self.map.remove_index(other);
+                if other < index {
+                    index -= 1;
+                }
+            }
+        }
+        self.map.debug_check_capacity();
+        let e = &mut self.map.entries[index];
+        e.key = new_key;
+        &mut e.val
+    }
+}
+
+/// A view into a vacant entry in a `RegistOrderMap`. It is part of the `Entry` enum.
+pub struct VacantEntry<'a, K, V> {
+    map: &'a mut RegistOrderMap<K, V>,
+    key: K,
+}
+
+// A bucket of `entries` whose value has been moved out. Dropping it removes the bucket,
+// dropping only its key, so the map stays valid if `replace_entry_with`'s closure panics.
+struct Hole<'a, K, V> {
+    entries: &'a mut Vec<Bucket<K, V>>,
+    index: usize,
+}
+
+impl<K, V> Hole<'_, K, V> {
+    // Removes the bucket, shifting the following ones, and returns its key.
+    fn take_key(&mut self) -> K {
+        let len = self.entries.len();
+        // SAFETY: `index < len`, and the bucket's value has already been moved out, so reading
+        // the key and moving the following buckets over it leaves every bucket up to `len - 1`
+        // initialized exactly once.
+        unsafe {
+            let bucket = self.entries.as_mut_ptr().add(self.index);
+            let key = std::ptr::read(&(*bucket).key);
+            std::ptr::copy(bucket.add(1), bucket, len - self.index - 1);
+            self.entries.set_len(len - 1);
+            key
+        }
+    }
+}
+
+impl<K, V> Drop for Hole<'_, K, V> {
+    fn drop(&mut self) {
+        drop(self.take_key());
+    }
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    /// Gets a reference to the key that would be used when inserting a value through the `VacantEntry`.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+    /// Returns the index the entry will occupy once inserted, which is the current length of the map.
+    pub fn index(&self) -> usize {
+        self.map.len()
+    }
+    /// Like `insert`, but the value is computed from the index the entry will occupy,
+    /// which is the length of the map before insertion.
+    pub fn insert_with_index<F>(self, f: F) -> &'a mut V
+    where
+        F: FnOnce(usize) -> V,
+    {
+        let v = f(self.index());
+        self.insert(v)
+    }
+    /// Sets the value of the entry with the `VacantEntry`'s key,
+    /// appending it to the end of the map, and returns a mutable reference to it.
+    pub fn insert(self, v: V) -> &'a mut V {
+        self.map.entries.push(Bucket {
+            key: self.key,
+            val: v,
+        });
+        self.map.debug_check_capacity();
+        &mut self.map.entries.last_mut().unwrap().val
+    }
+    /// Like `insert`, but also returns the index of the new entry,
+    /// which is the length of the map before insertion.
+    pub fn insert_full(self, v: V) -> (usize, &'a mut V) {
+        let index = self.index();
+        (index, self.insert(v))
+    }
+}
+
+/// A view into a single entry in a map obtained from a borrowed key,
+/// which may either be vacant or occupied.
+pub enum EntryRef<'a, 'q, K, Q: ?Sized, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntryRef<'a, 'q, K, Q, V>),
+}
+
+impl<'a, K, Q, V> EntryRef<'a, '_, K, Q, V>
+where
+    K: Borrow<Q>,
+    Q: ToOwned<Owned = K> + ?Sized,
+{
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &Q {
+        match self {
+            EntryRef::Occupied(entry) => entry.key().borrow(),
+            EntryRef::Vacant(entry) => entry.key(),
+        }
+    }
+    /// Ensures a value is in the entry by inserting the default if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default),
+        }
+    }
+    /// Ensures a value is in the entry by inserting the result of the default function if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_insert_with<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+}
+
+/// A view into a vacant entry in a `RegistOrderMap`, holding a borrowed key.
+/// It is part of the `EntryRef` enum.
+pub struct VacantEntryRef<'a, 'q, K, Q: ?Sized, V> {
+    map: &'a mut RegistOrderMap<K, V>,
+    key: &'q Q,
+}
+
+impl<'a, K, Q, V> VacantEntryRef<'a, '_, K, Q, V>
+where
+    Q: ToOwned<Owned = K> + ?Sized,
+{
+    /// Gets a reference to the borrowed key.
+    pub fn key(&self) -> &Q {
+        self.key
+    }
+    /// Sets the value of the entry with an owned copy of the key,
+    /// appending it to the end of the map, and returns a mutable reference to it.
+    pub fn insert(self, v: V) -> &'a mut V {
+        VacantEntry {
+            map: self.map,
+            key: self.key.to_owned(),
+        }
+        .insert(v)
+    }
+}
+
+pub struct Chunks<'a, K: 'a, V: 'a> {
+    inner: std::slice::Chunks<'a, Bucket<K, V>>,
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for Chunks<'a, K, V> {
+    type Item = Iter<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|chunk| Iter {
+            inner: chunk.iter(),
+        })
+    }
+}
+
+pub struct Windows<'a, K: 'a, V: 'a> {
+    inner: std::slice::Windows<'a, Bucket<K, V>>,
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for Windows<'a, K, V> {
+    type Item = Iter<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|window| Iter {
+            inner: window.iter(),
+        })
+    }
+}
+
 #[cfg(feature = "serde")]
 impl<K, V> Serialize for RegistOrderMap<K, V>
 where
@@ -150,80 +1535,1492 @@ where
     }
 }
 
-#[cfg(feature = "serde")]
-struct RegistOrderMapVisitor<K, V> {
-    marker: PhantomData<fn() -> RegistOrderMap<K, V>>,
+#[cfg(feature = "json")]
+impl<K, V> RegistOrderMap<K, V>
+where
+    K: Serialize + Eq,
+    V: Serialize,
+{
+    /// Converts the map into a `serde_json::Value`.
+    ///
+    /// With the `preserve_order` feature this is an object in registration order. Without it,
+    /// `serde_json` objects are sorted by key, so an array of `[key, value]` pairs is returned
+    /// instead to keep the order.
+    pub fn to_value(&self) -> Result<serde_json::Value, serde_json::Error> {
+        #[cfg(feature = "preserve_order")]
+        let value = serde_json::to_value(self);
+        #[cfg(not(feature = "preserve_order"))]
+        let value = serde_json::to_value(self.iter().collect::<Vec<_>>());
+        value
+    }
 }
 
+/// Serializes a `RegistOrderMap` with its entries sorted by key instead of in registration order,
+/// without modifying the map. Useful when a canonical representation is needed, e.g. for hashing.
 #[cfg(feature = "serde")]
-impl<K, V> RegistOrderMapVisitor<K, V> {
-    fn new() -> Self {
-        Self {
-            marker: PhantomData,
-        }
-    }
-}
+pub struct SortedSerialize<'a, K, V>(pub &'a RegistOrderMap<K, V>);
 
 #[cfg(feature = "serde")]
-impl<'de, K, V> Visitor<'de> for RegistOrderMapVisitor<K, V>
+impl<K, V> Serialize for SortedSerialize<'_, K, V>
 where
-    K: Deserialize<'de> + Eq,
+    K: Serialize + Ord,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut entries: Vec<&Bucket<K, V>> = self.0.entries.iter().collect();
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for e in entries {
+            map.serialize_entry(&e.key, &e.val)?;
+        }
+        map.end()
+    }
+}
+
+/// Serializes a `RegistOrderMap` of `Option` values, leaving out the entries whose value is `None`.
+/// The other entries are serialized with their inner value, in registration order.
+#[cfg(feature = "serde")]
+pub struct SkipNone<'a, K, V>(pub &'a RegistOrderMap<K, Option<V>>);
+
+#[cfg(feature = "serde")]
+impl<K, V> Serialize for SkipNone<'_, K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Count first, so that the length given to the serializer matches what is written.
+        let len = self.0.entries.iter().filter(|e| e.val.is_some()).count();
+        let mut map = serializer.serialize_map(Some(len))?;
+        for e in self.0.entries.iter() {
+            if let Some(val) = &e.val {
+                map.serialize_entry(&e.key, val)?;
+            }
+        }
+        map.end()
+    }
+}
+
+/// Serializes a slice of key-value pairs in the same format as a `RegistOrderMap`, in slice order.
+/// Duplicate keys are not detected and are written as they are.
+#[cfg(feature = "serde")]
+pub struct SerializeOrdered<'a, K, V>(pub &'a [(K, V)]);
+
+#[cfg(feature = "serde")]
+impl<K, V> Serialize for SerializeOrdered<'_, K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (k, v) in self.0 {
+            map.serialize_entry(k, v)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+struct RegistOrderMapVisitor<K, V> {
+    marker: PhantomData<fn() -> RegistOrderMap<K, V>>,
+}
+
+#[cfg(feature = "serde")]
+impl<K, V> RegistOrderMapVisitor<K, V> {
+    fn new() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V> Visitor<'de> for RegistOrderMapVisitor<K, V>
+where
+    K: Deserialize<'de> + Eq,
     V: Deserialize<'de>,
 {
     type Value = RegistOrderMap<K, V>;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("a very special map")
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a very special map")
+    }
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        // Trust the size hint only up to about 1 MiB of entries, like serde's own collections,
+        // so that a bogus length in the input can't trigger a huge allocation up front.
+        // Past that, the storage grows geometrically as entries arrive.
+        let max = 1024 * 1024 / std::mem::size_of::<Bucket<K, V>>().max(1);
+        let mut map = RegistOrderMap::with_capacity(access.size_hint().unwrap_or(0).min(max));
+
+        while let Some((key, value)) = access.next_entry()? {
+            map.insert(key, value);
+        }
+
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V> Deserialize<'de> for RegistOrderMap<K, V>
+where
+    K: Deserialize<'de> + Eq,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Instantiate our Visitor and ask the Deserializer to drive
+        // it over the input data, resulting in an instance of MyMap.
+        deserializer.deserialize_map(RegistOrderMapVisitor::new())
+    }
+}
+
+/// Deserializes a `RegistOrderMap` in registration order, for use with
+/// `#[serde(deserialize_with = "registorder_map::deserialize_regist_order_map")]`
+/// on fields where the `Deserialize` impl can't be picked up directly.
+#[cfg(feature = "serde")]
+pub fn deserialize_regist_order_map<'de, D, K, V>(
+    deserializer: D,
+) -> Result<RegistOrderMap<K, V>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: Deserialize<'de> + Eq,
+    V: Deserialize<'de>,
+{
+    RegistOrderMap::deserialize(deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    // Counts allocations and reallocations per thread, so tests running in parallel don't interfere.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn count_allocation() {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count_allocation();
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count_allocation();
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Returns the number of allocations and reallocations made by the current thread while running `f`.
+    fn allocations<T>(f: impl FnOnce() -> T) -> (usize, T) {
+        let before = ALLOCATIONS.with(Cell::get);
+        let result = f();
+        (ALLOCATIONS.with(Cell::get) - before, result)
+    }
+
+    /// Returns an xorshift64 generator. Randomized tests use a fixed seed, so that failures reproduce.
+    fn xorshift64(mut state: u64) -> impl FnMut() -> u64 {
+        move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        }
+    }
+
+    thread_local! {
+        static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    // A key that counts how often it is compared, per thread like the allocations.
+    #[derive(Clone, Debug)]
+    struct CountedKey(u32);
+
+    impl PartialEq for CountedKey {
+        fn eq(&self, other: &Self) -> bool {
+            COMPARISONS.with(|n| n.set(n.get() + 1));
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for CountedKey {}
+
+    /// Builds a map of `CountedKey(i) => i` for `i` in `0..n` directly, without comparing any key.
+    fn counted_map(n: u32) -> RegistOrderMap<CountedKey, u32> {
+        RegistOrderMap {
+            entries: (0..n)
+                .map(|i| Bucket {
+                    key: CountedKey(i),
+                    val: i,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the number of key comparisons made by the current thread while running `f`.
+    fn comparisons<T>(f: impl FnOnce() -> T) -> (usize, T) {
+        let before = COMPARISONS.with(Cell::get);
+        let result = f();
+        (COMPARISONS.with(Cell::get) - before, result)
+    }
+
+    #[test]
+    fn test_insert() {
+        let key1 = "key1".to_string();
+        let key2 = "key2".to_string();
+        let mut map = RegistOrderMap::new();
+        map.insert(key2.clone(), 20);
+        assert_eq!(map.get(&key1), None);
+        assert_eq!(map.get(&key2), Some(&20));
+        map.insert(key1.clone(), 10);
+        assert_eq!(map.get(&key1), Some(&10));
+        assert_eq!(map.get(&key2), Some(&20));
+    }
+
+    #[test]
+    fn test_get_or_default() {
+        let mut map = RegistOrderMap::new();
+        map.insert("key1", 10);
+        let default = 0;
+        assert_eq!(map.get_or_default(&"key1", &default), &10);
+        assert_eq!(map.get_or_default(&"key2", &default), &0);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_entry_or_insert_with_result() {
+        let mut map = RegistOrderMap::new();
+        map.insert("key1", 10);
+        let v = map
+            .entry("key2")
+            .or_insert_with_result(|| "20".parse::<i32>())
+            .unwrap();
+        assert_eq!(*v, 20);
+        let v = map
+            .entry("key1")
+            .or_insert_with_result(|| "x".parse::<i32>())
+            .unwrap();
+        assert_eq!(*v, 10);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key1", &10)));
+        assert_eq!(iter.next(), Some((&"key2", &20)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_entry_or_insert_with_result_err() {
+        let mut map = RegistOrderMap::new();
+        map.insert("key1", 10);
+        let result = map
+            .entry("key2")
+            .or_insert_with_result(|| "x".parse::<i32>());
+        assert!(result.is_err());
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&"key2"), None);
+    }
+
+    #[cfg(feature = "key-mut")]
+    #[test]
+    fn test_key_mut() {
+        #[derive(Debug)]
+        struct Key {
+            id: u32,
+            label: &'static str,
+        }
+        impl PartialEq for Key {
+            fn eq(&self, other: &Self) -> bool {
+                self.id == other.id
+            }
+        }
+        impl Eq for Key {}
+
+        let mut map = RegistOrderMap::new();
+        map.insert(
+            Key {
+                id: 1,
+                label: "one",
+            },
+            10,
+        );
+        map.insert(
+            Key {
+                id: 2,
+                label: "two",
+            },
+            20,
+        );
+        match map.entry(Key { id: 2, label: "" }) {
+            Entry::Occupied(mut entry) => entry.key_mut().label = "deux",
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(map.get(&Key { id: 2, label: "" }), Some(&20));
+        assert_eq!(map.get_index(1).unwrap().0.label, "deux");
+        assert_eq!(map.get_index(0).unwrap().0.label, "one");
+    }
+
+    #[test]
+    fn test_occupied_insert_keeps_position() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        match map.entry("key1") {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert(11), 10);
+                assert_eq!(entry.index(), 0);
+                assert_eq!(entry.get(), &11);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert_eq!(map.get_index_of("key1"), Some(0));
+        assert_eq!(map.as_pairs(), &[("key1", 11), ("key2", 20), ("key3", 30)]);
+    }
+
+    #[test]
+    fn test_vacant_insert_full() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20)]);
+        let len = map.len();
+        let index = match map.entry("key3") {
+            Entry::Vacant(entry) => {
+                let (index, v) = entry.insert_full(30);
+                *v += 1;
+                index
+            }
+            Entry::Occupied(_) => unreachable!(),
+        };
+        assert_eq!(index, len);
+        assert_eq!(map.get_index(index), Some((&"key3", &31)));
+    }
+
+    #[test]
+    fn test_entry_index() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20)]);
+        let index = match map.entry("key3") {
+            Entry::Occupied(_) => unreachable!(),
+            Entry::Vacant(entry) => {
+                let index = entry.index();
+                entry.insert(30);
+                index
+            }
+        };
+        assert_eq!(index, 2);
+        map.move_index(index, 0);
+        let index = match map.entry("key1") {
+            Entry::Occupied(entry) => entry.index(),
+            Entry::Vacant(_) => unreachable!(),
+        };
+        assert_eq!(index, 1);
+        map.move_index(index, 2);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key3", &30)));
+        assert_eq!(iter.next(), Some((&"key2", &20)));
+        assert_eq!(iter.next(), Some((&"key1", &10)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_retain_mutates_kept() {
+        let mut map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 3), ("key4", 4)]);
+        map.retain(|_, v| {
+            *v *= 10;
+            *v != 20
+        });
+        assert_eq!(map.as_pairs(), &[("key1", 10), ("key3", 30), ("key4", 40)]);
+    }
+
+    #[test]
+    fn test_retain_count() {
+        let mut map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 3), ("key4", 4)]);
+        assert_eq!(map.retain_count(|_, v| *v % 2 == 0), 2);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key2", &2)));
+        assert_eq!(iter.next(), Some((&"key4", &4)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(map.retain_count(|_, _| true), 0);
+    }
+
+    #[test]
+    fn test_into_retained() {
+        let map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 3), ("key4", 4)]);
+        let even = map.clone().into_retained(|_, v| *v % 2 == 0);
+        assert_eq!(even.as_pairs(), &[("key2", 2), ("key4", 4)]);
+        let keys: Vec<&str> = map.into_retained(|k, _| *k != "key2").into_keys().collect();
+        assert_eq!(keys, ["key1", "key3", "key4"]);
+    }
+
+    #[test]
+    fn test_retain_indexed() {
+        let mut map: RegistOrderMap<_, _> =
+            ["a", "b", "c", "d", "e"].into_iter().zip(0..).collect();
+        let mut seen = Vec::new();
+        map.retain_indexed(|i, k, v| {
+            seen.push(i);
+            *v += 10;
+            i % 2 == 0 && *k != "e"
+        });
+        assert_eq!(seen, [0, 1, 2, 3, 4]);
+        assert_eq!(map.as_pairs(), &[("a", 10), ("c", 12)]);
+    }
+
+    #[test]
+    fn test_eq() {
+        let a = RegistOrderMap::from([("key1", 10), ("key2", 20)]);
+        let b = RegistOrderMap::from([("key2", 20), ("key1", 10)]);
+        assert_eq!(a, a.clone());
+        assert_ne!(a, b);
+        assert!(a.eq_unordered(&b));
+        assert_ne!(a, RegistOrderMap::from([("key1", 10), ("key2", 21)]));
+        assert!(!a.eq_unordered(&RegistOrderMap::from([("key2", 21), ("key1", 10)])));
+        assert!(!a.eq_unordered(&RegistOrderMap::from([("key2", 20)])));
+        assert!(!RegistOrderMap::from([("key2", 20)]).eq_unordered(&a));
+    }
+
+    #[test]
+    fn test_group_by_value() {
+        let map = RegistOrderMap::from([("key1", 20), ("key2", 10), ("key3", 20), ("key4", 30)]);
+        let groups = map.group_by_value();
+        let mut iter = groups.iter();
+        assert_eq!(iter.next(), Some((&20, &vec![&"key1", &"key3"])));
+        assert_eq!(iter.next(), Some((&10, &vec![&"key2"])));
+        assert_eq!(iter.next(), Some((&30, &vec![&"key4"])));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_is_sorted_by_key() {
+        let map = RegistOrderMap::from([("key3", 10), ("key1", 20), ("key2", 20)]);
+        assert!(map.is_sorted_by_key(|_, v| *v));
+        assert!(!map.is_sorted_by_key(|k, _| *k));
+        let map: RegistOrderMap<&str, i32> = RegistOrderMap::new();
+        assert!(map.is_sorted_by_key(|k, _| *k));
+    }
+
+    #[test]
+    fn test_binary_search_by_key() {
+        let mut map = RegistOrderMap::from([("key3", 30), ("key1", 10), ("key4", 40)]);
+        map.sort_keys();
+        assert_eq!(map.binary_search_by_key(&"key1", |k, _| *k), Ok(0));
+        assert_eq!(map.binary_search_by_key(&"key4", |k, _| *k), Ok(2));
+        assert_eq!(map.binary_search_by_key(&"key2", |k, _| *k), Err(1));
+        assert_eq!(map.binary_search_by_key(&"key5", |k, _| *k), Err(3));
+        map.sort_by(|_, a, _, b| b.cmp(a));
+        assert_eq!(
+            map.binary_search_by_key(&std::cmp::Reverse(30), |_, v| std::cmp::Reverse(*v)),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn test_as_pairs() {
+        let mut map = RegistOrderMap::new();
+        map.insert("key2".to_string(), 20i64);
+        map.insert("key1".to_string(), 10i64);
+        assert_eq!(
+            map.as_pairs(),
+            &[("key2".to_string(), 20), ("key1".to_string(), 10)]
+        );
+        let map = RegistOrderMap::from([(1u8, 10u32), (2u8, 20u32)]);
+        assert_eq!(map.as_pairs(), &[(1, 10), (2, 20)]);
+        let map: RegistOrderMap<u64, ()> = RegistOrderMap::new();
+        assert!(map.as_pairs().is_empty());
+    }
+
+    #[test]
+    fn test_pairs() {
+        fn sum<'a>(pairs: impl Iterator<Item = &'a (&'a str, i32)>) -> i32 {
+            pairs.map(|(_, v)| v).sum()
+        }
+
+        let map = RegistOrderMap::from([("key2", 20), ("key1", 10), ("key3", 30)]);
+        assert_eq!(
+            map.pairs().cloned().collect::<Vec<_>>(),
+            [("key2", 20), ("key1", 10), ("key3", 30)]
+        );
+        assert_eq!(sum(map.pairs()), 60);
+    }
+
+    #[test]
+    fn test_into_pairs() {
+        let mut map = RegistOrderMap::new();
+        map.insert("key2".to_string(), 20);
+        map.insert("key1".to_string(), 10);
+        assert_eq!(
+            map.into_pairs(),
+            vec![("key2".to_string(), 20), ("key1".to_string(), 10)]
+        );
+    }
+
+    #[test]
+    fn test_move_to_front_back() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        assert!(map.move_to_back(&"key2"));
+        let keys: Vec<&str> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, ["key1", "key3", "key2"]);
+        assert!(map.move_to_front(&"key3"));
+        let keys: Vec<&str> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, ["key3", "key1", "key2"]);
+        assert!(!map.move_to_front(&"key4"));
+        assert!(!map.move_to_back(&"key4"));
+        assert_eq!(map.get(&"key2"), Some(&20));
+    }
+
+    #[test]
+    fn test_drain_filter_ordered() {
+        let mut map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 3), ("key4", 4)]);
+        let removed: Vec<(&str, i32)> = map.drain_filter_ordered(|_, v| *v % 2 == 1).collect();
+        assert_eq!(removed, [("key1", 1), ("key3", 3)]);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key2", &2)));
+        assert_eq!(iter.next(), Some((&"key4", &4)));
+        assert_eq!(iter.next(), None);
+
+        let mut map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 3), ("key4", 4)]);
+        let mut drain = map.drain_filter_ordered(|_, v| *v % 2 == 1);
+        assert_eq!(drain.next(), Some(("key1", 1)));
+        drop(drain);
+        let keys: Vec<&str> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, ["key2", "key3", "key4"]);
+
+        // The kept entries are compacted within the map's own storage.
+        let ptr = map.backing_ptr();
+        let (count, removed) = allocations(|| map.drain_filter_ordered(|_, v| *v == 3).count());
+        assert_eq!((count, removed), (0, 1));
+        assert_eq!(map.backing_ptr(), ptr);
+        assert_eq!(map.as_pairs(), &[("key2", 2), ("key4", 4)]);
+
+        // A panicking predicate keeps the entry it was called with and all later ones.
+        let mut map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 3), ("key4", 4)]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            map.drain_filter_ordered(|_, v| *v == 2 || (*v == 3 && panic!()))
+                .count()
+        }));
+        assert!(result.is_err());
+        assert_eq!(map.as_pairs(), &[("key1", 1), ("key3", 3), ("key4", 4)]);
+    }
+
+    fn assert_many_entries(n: u32) {
+        let mut map = RegistOrderMap::new();
+        // Registered in an order different from the key order, to catch any sorting.
+        let key = |i: u32| i.wrapping_mul(2_654_435_761);
+        for i in 0..n {
+            map.insert(key(i), i);
+        }
+        assert_eq!(map.len(), n as usize);
+        assert_eq!(map.iter().count(), n as usize);
+        assert!(map
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..n).map(|i| (key(i), i))));
+        assert_eq!(map.get(&key(n - 1)), Some(&(n - 1)));
+        assert_eq!(map.get_index_of(&key(n / 2)), Some(n as usize / 2));
+    }
+
+    #[test]
+    fn test_many_entries() {
+        assert_many_entries(5_000);
+    }
+
+    // Inserting is quadratic with the linear lookup, so this takes minutes even in release mode:
+    // `cargo test --release -- --ignored test_million_entries`.
+    #[test]
+    #[ignore]
+    fn test_million_entries() {
+        assert_many_entries(1_000_000);
+    }
+
+    #[test]
+    fn test_drain_filter_ordered_large() {
+        // Built directly, as inserting one by one is quadratic with the linear lookup.
+        let mut map = RegistOrderMap {
+            entries: (0..100_000).map(|i| Bucket { key: i, val: i }).collect(),
+        };
+        let removed: Vec<(i32, i32)> = map.drain_filter_ordered(|_, v| *v % 2 == 1).collect();
+        assert_eq!(removed.len(), 50_000);
+        assert!(removed.iter().map(|(k, _)| *k).eq((1..100_000).step_by(2)));
+        assert!(map.iter().map(|(k, _)| *k).eq((0..100_000).step_by(2)));
+    }
+
+    #[test]
+    fn test_entry_key() {
+        let mut map = RegistOrderMap::new();
+        let entry = map.entry("key1".to_string());
+        assert_eq!(entry.key(), "key1");
+        entry.or_insert(10);
+        let entry = map.entry("key1".to_string());
+        assert_eq!(entry.key(), "key1");
+        assert_eq!(*entry.or_insert(20), 10);
+    }
+
+    #[test]
+    fn test_try_retain() {
+        let mut map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 3), ("key4", 4)]);
+        assert_eq!(map.try_retain(|_, v| Ok::<_, ()>(*v % 2 == 0)), Ok(()));
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key2", &2)));
+        assert_eq!(iter.next(), Some((&"key4", &4)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_try_retain_err() {
+        let mut map = RegistOrderMap::from([
+            ("key1", 1),
+            ("key2", 2),
+            ("key3", 3),
+            ("key4", 4),
+            ("key5", 5),
+        ]);
+        let mut visited = Vec::new();
+        let result = map.try_retain(|k, v| {
+            visited.push(*k);
+            if *v == 3 {
+                *v = 30;
+                return Err("failed");
+            }
+            Ok(*v % 2 == 0)
+        });
+        assert_eq!(result, Err("failed"));
+        assert_eq!(visited, ["key1", "key2", "key3"]);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key2", &2)));
+        assert_eq!(iter.next(), Some((&"key3", &30)));
+        assert_eq!(iter.next(), Some((&"key4", &4)));
+        assert_eq!(iter.next(), Some((&"key5", &5)));
+        assert_eq!(iter.next(), None);
+
+        // A panicking predicate keeps the entry it was called with and all later ones.
+        let mut map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 3), ("key4", 4)]);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            map.try_retain(|_, v| match *v {
+                1 => Ok::<_, ()>(false),
+                3 => panic!(),
+                _ => Ok(true),
+            })
+        }));
+        assert!(result.is_err());
+        assert_eq!(map.as_pairs(), &[("key2", 2), ("key3", 3), ("key4", 4)]);
+    }
+
+    #[test]
+    fn test_with_exact_capacity() {
+        let mut map = RegistOrderMap::with_exact_capacity(10);
+        assert_eq!(map.capacity(), 10);
+        for i in 0..10 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.capacity(), 10);
+        map.reserve_exact(3);
+        assert_eq!(map.capacity(), 13);
+        assert_eq!(
+            RegistOrderMap::<u8, u8>::with_exact_capacity(0).capacity(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_shrink_to() {
+        let mut map = RegistOrderMap::with_capacity(1024);
+        map.insert("key1", 10);
+        map.insert("key2", 20);
+        map.shrink_to(8);
+        assert!(map.capacity() >= 8);
+        assert!(map.capacity() < 1024);
+        map.shrink_to(0);
+        assert!(map.capacity() >= 2);
+        assert_eq!(map.get(&"key2"), Some(&20));
+    }
+
+    #[test]
+    fn test_clone_into() {
+        let source = RegistOrderMap::from([(2, 20), (1, 10), (3, 30)]);
+        let mut target: RegistOrderMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        let ptr = target.backing_ptr();
+        let (count, ()) = allocations(|| source.clone_into(&mut target));
+        assert_eq!(count, 0);
+        assert_eq!(target, source);
+        assert_eq!(target.backing_ptr(), ptr);
+
+        let mut small = RegistOrderMap::new();
+        source.clone_into(&mut small);
+        assert_eq!(small, source);
+    }
+
+    #[test]
+    fn test_clone_compact() {
+        let mut map = RegistOrderMap::with_capacity(1024);
+        map.insert("key2", 20);
+        map.insert("key1", 10);
+        let (count, compact) = allocations(|| map.clone_compact());
+        assert_eq!(count, 1);
+        assert!(compact.capacity() >= 2);
+        assert!(compact.capacity() < 8);
+        assert!(compact.iter().eq(map.iter()));
+        assert_eq!(map.capacity(), 1024);
+    }
+
+    // Growing and shrinking go through the `len() <= capacity()` debug assertion at every step.
+    #[test]
+    fn test_len_within_capacity() {
+        let mut map = RegistOrderMap::new();
+        for i in 0..100 {
+            map.insert(i, i);
+            assert!(map.len() <= map.capacity());
+        }
+        map.reserve(1000);
+        assert!(map.capacity() >= 1100);
+        map.retain(|k, _| k % 10 == 0);
+        map.shrink_to(0);
+        assert_eq!(map.len(), 10);
+        assert!(map.capacity() >= 10);
+        while map.pop_front().is_some() {
+            assert!(map.len() <= map.capacity());
+        }
+        map.shrink_to(0);
+        assert_eq!(map.capacity(), 0);
+        map.entry(1).or_insert(1);
+        assert!(map.len() <= map.capacity());
+    }
+
+    #[test]
+    fn test_with_capacity_no_reallocation() {
+        let mut map = RegistOrderMap::with_capacity(1000);
+        let capacity = map.capacity();
+        let ptr = map.backing_ptr();
+        let (count, ()) = allocations(|| {
+            for i in 0..1000 {
+                map.insert(i, i);
+            }
+        });
+        assert_eq!(count, 0);
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.backing_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_clear_reuses_allocation() {
+        let mut map: RegistOrderMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        let ptr = map.backing_ptr();
+        map.clear();
+        map.extend((100..200).map(|i| (i, i)));
+        assert_eq!(map.backing_ptr(), ptr);
+        map.retain(|k, _| k % 2 == 0);
+        map.insert(-1, -1);
+        assert_eq!(map.backing_ptr(), ptr);
+        assert_eq!(map.len(), 51);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        let capacity = map.capacity();
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.get(&"key1"), None);
+        map.insert("key3", 31);
+        map.insert("key1", 11);
+        assert_eq!(map.get(&"key1"), Some(&11));
+        assert_eq!(map.get(&"key2"), None);
+        assert_eq!(map.get(&"key3"), Some(&31));
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key3", &31)));
+        assert_eq!(iter.next(), Some((&"key1", &11)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_vacant_insert_with_index() {
+        let mut map = RegistOrderMap::new();
+        for name in ["zeta", "alpha", "zeta", "mu"] {
+            let len = map.len();
+            match map.entry(name) {
+                Entry::Occupied(_) => {}
+                Entry::Vacant(entry) => {
+                    entry.insert_with_index(|index| {
+                        assert_eq!(index, len);
+                        index
+                    });
+                }
+            }
+        }
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"zeta", &0)));
+        assert_eq!(iter.next(), Some((&"alpha", &1)));
+        assert_eq!(iter.next(), Some((&"mu", &2)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_empty_capacity() {
+        let (count, map) = allocations(RegistOrderMap::<String, String>::new);
+        assert_eq!(count, 0);
+        assert_eq!(map.capacity(), 0);
+        let (count, map) = allocations(RegistOrderMap::<String, String>::default);
+        assert_eq!(count, 0);
+        assert_eq!(map.capacity(), 0);
+    }
+
+    #[test]
+    fn test_entry_ref() {
+        let text = "the cat saw the dog and the cat ran";
+        let mut map: RegistOrderMap<String, usize> = RegistOrderMap::with_capacity(16);
+        let (count, ()) = allocations(|| {
+            for word in text.split(' ') {
+                *map.entry_ref(word).or_insert(0) += 1;
+            }
+        });
+        // Only the six distinct words allocate a `String`.
+        assert_eq!(count, 6);
+        let counts: Vec<(&str, usize)> = map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(
+            counts,
+            [
+                ("the", 3),
+                ("cat", 2),
+                ("saw", 1),
+                ("dog", 1),
+                ("and", 1),
+                ("ran", 1)
+            ]
+        );
+        assert_eq!(map.entry_ref("the").key(), "the");
+        assert_eq!(map.entry_ref("fox").key(), "fox");
+    }
+
+    #[test]
+    fn test_get_or_insert_ref() {
+        let mut map: RegistOrderMap<String, usize> = RegistOrderMap::new();
+        let mut made = Vec::new();
+        for word in ["cat", "dog", "cat", "cat", "bird"] {
+            *map.get_or_insert_ref(word, |w| {
+                made.push(w.to_string());
+                (w.to_string(), 0)
+            }) += 1;
+        }
+        assert_eq!(made, ["cat", "dog", "bird"]);
+        let counts: Vec<(&str, usize)> = map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(counts, [("cat", 3), ("dog", 1), ("bird", 1)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_or_insert_ref_mismatched_key() {
+        let mut map: RegistOrderMap<String, usize> = RegistOrderMap::new();
+        map.insert("a".to_string(), 1);
+        map.get_or_insert_ref("b", |_| ("a".to_string(), 0));
+    }
+
+    // A high hit rate workload: `entry` allocates a key for every lookup, `entry_ref` only on a miss.
+    #[test]
+    fn test_entry_ref_allocations() {
+        let words: Vec<String> = (0..1000).map(|i| format!("word{}", i % 10)).collect();
+
+        let mut map: RegistOrderMap<String, usize> = RegistOrderMap::with_capacity(10);
+        let (owned, ()) = allocations(|| {
+            for word in &words {
+                *map.entry(word.clone()).or_insert(0) += 1;
+            }
+        });
+        let mut map_ref: RegistOrderMap<String, usize> = RegistOrderMap::with_capacity(10);
+        let (borrowed, ()) = allocations(|| {
+            for word in &words {
+                *map_ref.entry_ref(word.as_str()).or_insert(0) += 1;
+            }
+        });
+        assert_eq!(owned, 1000);
+        assert_eq!(borrowed, 10);
+        assert!(map.iter().eq(map_ref.iter()));
+    }
+
+    #[test]
+    fn test_replace_entry_with() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        let entry = match map.entry("key2") {
+            Entry::Occupied(entry) => entry.replace_entry_with(|k, v| {
+                assert_eq!(k, &"key2");
+                Some(v + 1)
+            }),
+            Entry::Vacant(_) => unreachable!(),
+        };
+        match entry {
+            Entry::Occupied(entry) => assert_eq!(entry.index(), 1),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key1", &10)));
+        assert_eq!(iter.next(), Some((&"key2", &21)));
+        assert_eq!(iter.next(), Some((&"key3", &30)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_occupied_remove_full() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        let position = map.get_index_of(&"key2").unwrap();
+        let (index, k, v) = match map.entry("key2") {
+            Entry::Occupied(entry) => entry.remove_full(),
+            Entry::Vacant(_) => unreachable!(),
+        };
+        assert_eq!((index, k, v), (position, "key2", 20));
+        assert_eq!(map.as_pairs(), &[("key1", 10), ("key3", 30)]);
+        map.shift_insert(index, k, v);
+        assert_eq!(map.as_pairs(), &[("key1", 10), ("key2", 20), ("key3", 30)]);
+    }
+
+    #[test]
+    fn test_remove_and_reinsert() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        match map.entry("key2") {
+            Entry::Occupied(entry) => *entry.remove_and_reinsert("renamed") += 1,
+            Entry::Vacant(_) => unreachable!(),
+        }
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, [("key1", 10), ("renamed", 21), ("key3", 30)]);
+
+        // Taking the key of another entry removes that entry.
+        match map.entry("key3") {
+            Entry::Occupied(entry) => assert_eq!(*entry.remove_and_reinsert("key1"), 30),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, [("renamed", 21), ("key1", 30)]);
+    }
+
+    #[test]
+    fn test_replace_entry_with_remove() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        let entry = match map.entry("key1") {
+            Entry::Occupied(entry) => entry.replace_entry_with(|_, _| None),
+            Entry::Vacant(_) => unreachable!(),
+        };
+        assert_eq!(entry.key(), &"key1");
+        assert!(matches!(entry, Entry::Vacant(_)));
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key2", &20)));
+        assert_eq!(iter.next(), Some((&"key3", &30)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_replace_entry_with_panic() {
+        use std::rc::Rc;
+        let key = Rc::new("key2");
+        let val = Rc::new(20);
+        let mut map = RegistOrderMap::new();
+        map.insert(Rc::new("key1"), Rc::new(10));
+        map.insert(key.clone(), val.clone());
+        map.insert(Rc::new("key3"), Rc::new(30));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            if let Entry::Occupied(entry) = map.entry(key.clone()) {
+                entry.replace_entry_with(|_, _| panic!());
+            }
+        }));
+        assert!(result.is_err());
+        assert_eq!(Rc::strong_count(&key), 1);
+        assert_eq!(Rc::strong_count(&val), 1);
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (**k, **v)).collect();
+        assert_eq!(pairs, [("key1", 10), ("key3", 30)]);
+    }
+
+    #[test]
+    fn test_try_reserve() {
+        let mut map: RegistOrderMap<&str, i32> = RegistOrderMap::new();
+        assert_eq!(map.try_reserve(10), Ok(()));
+        assert!(map.try_reserve(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_get_index_mut() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        let (k, v) = map.get_index_mut(1).unwrap();
+        assert_eq!(k, &"key2");
+        *v = 25;
+        assert!(map.get_index_mut(3).is_none());
+        assert_eq!(map.get_index(0), Some((&"key1", &10)));
+        assert_eq!(map.get_index(1), Some((&"key2", &25)));
+        assert_eq!(map.get_index(2), Some((&"key3", &30)));
+        assert_eq!(map.get_index(3), None);
+    }
+
+    #[test]
+    fn test_shift_insert() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20)]);
+        assert_eq!(map.shift_insert(0, "key0", 0), None);
+        assert_eq!(map.shift_insert(3, "key3", 30), None);
+        assert_eq!(
+            map.as_pairs(),
+            &[("key0", 0), ("key1", 10), ("key2", 20), ("key3", 30)]
+        );
+    }
+
+    #[test]
+    fn test_shift_insert_existing() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        assert_eq!(map.shift_insert(0, "key3", 31), Some(30));
+        assert_eq!(map.as_pairs(), &[("key3", 31), ("key1", 10), ("key2", 20)]);
+        assert_eq!(map.shift_insert(2, "key3", 32), Some(31));
+        assert_eq!(map.as_pairs(), &[("key1", 10), ("key2", 20), ("key3", 32)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_shift_insert_out_of_bounds() {
+        let mut map = RegistOrderMap::from([("key1", 10)]);
+        map.shift_insert(2, "key2", 20);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_shift_insert_existing_out_of_bounds() {
+        let mut map = RegistOrderMap::from([("key1", 10)]);
+        map.shift_insert(1, "key1", 11);
+    }
+
+    #[test]
+    fn test_insert_or_get() {
+        let mut map = RegistOrderMap::new();
+        let (v, inserted) = map.insert_or_get("key1", 10);
+        assert_eq!((*v, inserted), (10, true));
+        *v += 1;
+        let (v, inserted) = map.insert_or_get("key1", 20);
+        assert_eq!((*v, inserted), (11, false));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_many() {
+        let mut map = RegistOrderMap::from([("key2", 20), ("key1", 10)]);
+        map.insert_many([("key3", 0), ("key1", 0), ("key4", 0), ("key3", 1)]);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key2", &20)));
+        assert_eq!(iter.next(), Some((&"key1", &10)));
+        assert_eq!(iter.next(), Some((&"key3", &0)));
+        assert_eq!(iter.next(), Some((&"key4", &0)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_ensure_keys() {
+        let mut map = RegistOrderMap::from([("key2", 20), ("key1", 10)]);
+        let mut calls = Vec::new();
+        map.ensure_keys(["key1", "key4", "key3", "key4"], |k| {
+            calls.push(*k);
+            k.len() as i32
+        });
+        assert_eq!(calls, ["key4", "key3"]);
+        assert_eq!(
+            map.as_pairs(),
+            &[("key2", 20), ("key1", 10), ("key4", 4), ("key3", 4)]
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        assert_eq!(map.remove(&"key2"), Some(20));
+        assert_eq!(map.remove(&"key2"), None);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key1", &10)));
+        assert_eq!(iter.next(), Some((&"key3", &30)));
+        assert_eq!(iter.next(), None);
+    }
+
+    // Checks the central guarantee against a plain list of pairs: iteration follows the order
+    // of first insertion, overwriting keeps the position and removed keys are gone.
+    #[test]
+    fn test_deterministic_order() {
+        let mut next = xorshift64(0x2545_f491_4f6c_dd1d);
+        let mut map = RegistOrderMap::new();
+        let mut model: Vec<(u64, u64)> = Vec::new();
+        for step in 0..10_000 {
+            let k = next() % 64;
+            match next() % 3 {
+                0 => {
+                    map.insert(k, step);
+                    match model.iter_mut().find(|(mk, _)| *mk == k) {
+                        Some((_, mv)) => *mv = step,
+                        None => model.push((k, step)),
+                    }
+                }
+                1 => {
+                    let expected = model
+                        .iter()
+                        .position(|(mk, _)| *mk == k)
+                        .map(|i| model.remove(i).1);
+                    assert_eq!(map.remove(&k), expected);
+                }
+                _ => {
+                    let expected = model.iter().find(|(mk, _)| *mk == k).map(|(_, mv)| mv);
+                    assert_eq!(map.get(&k), expected);
+                }
+            }
+            assert_eq!(map.len(), model.len());
+            assert!(
+                map.iter().eq(model.iter().map(|(k, v)| (k, v))),
+                "step {step}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_get_full_or_insert_with() {
+        let mut map = RegistOrderMap::from([("key1", 10)]);
+        let (index, v) = map.get_full_or_insert_with("key2", || 20);
+        assert_eq!((index, *v), (1, 20));
+        *v += 1;
+        let (index, v) = map.get_full_or_insert_with("key2", || unreachable!());
+        assert_eq!((index, *v), (1, 21));
+        let (index, v) = map.get_full_or_insert_with("key1", || unreachable!());
+        assert_eq!((index, *v), (0, 10));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_replace_if_eq() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20)]);
+        assert_eq!(map.replace_if_eq(&"key1", &10, 11), Ok(()));
+        assert_eq!(map.get(&"key1"), Some(&11));
+        assert_eq!(map.replace_if_eq(&"key1", &10, 12), Err(Some(11)));
+        assert_eq!(map.get(&"key1"), Some(&11));
+        assert_eq!(map.replace_if_eq(&"key3", &10, 30), Err(None));
+        assert_eq!(map.len(), 2);
+
+        // Retry with the current value, as in an optimistic update.
+        if let Err(Some(cur)) = map.replace_if_eq(&"key2", &0, 0) {
+            assert_eq!(map.replace_if_eq(&"key2", &cur, cur + 1), Ok(()));
+        }
+        assert_eq!(map.get(&"key2"), Some(&21));
+    }
+
+    #[test]
+    fn test_upsert_many() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20)]);
+        let previous = map.upsert_many([("key2", 21), ("key3", 30), ("key1", 11), ("key3", 31)]);
+        assert_eq!(previous, [Some(20), None, Some(10), Some(30)]);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key1", &11)));
+        assert_eq!(iter.next(), Some((&"key2", &21)));
+        assert_eq!(iter.next(), Some((&"key3", &31)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_get_or_insert_default() {
+        let mut map = RegistOrderMap::from([("key1", 10)]);
+        *map.get_or_insert_default("key2") += 5;
+        *map.get_or_insert_default("key1") += 5;
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key1", &15)));
+        assert_eq!(iter.next(), Some((&"key2", &5)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_cow_keys() {
+        use std::borrow::Cow;
+        let mut map: RegistOrderMap<Cow<str>, i32> = RegistOrderMap::new();
+        map.insert(Cow::Borrowed("key1"), 10);
+        map.insert(Cow::Owned("key1".to_string()), 11);
+        map.insert(Cow::Owned("key2".to_string()), 20);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("key1"), Some(&11));
+        assert_eq!(map.get("key2"), Some(&20));
+        assert_eq!(map.remove("key1"), Some(11));
+        assert_eq!(map.get("key1"), None);
+    }
+
+    // Every lookup goes through `find`, so they all agree on membership.
+    #[test]
+    fn test_lookups_agree() {
+        let mut map: RegistOrderMap<String, i32> = RegistOrderMap::new();
+        map.insert("key1".to_string(), 10);
+        map.insert("key2".to_string(), 20);
+        for (k, present) in [("key1", true), ("key2", true), ("key3", false), ("", false)] {
+            assert_eq!(map.get(k).is_some(), present, "{}", k);
+            assert_eq!(map.get_mut(k).is_some(), present, "{}", k);
+            assert_eq!(map.contains_key(k), present, "{}", k);
+            assert_eq!(
+                matches!(map.entry_ref(k), EntryRef::Occupied(_)),
+                present,
+                "{}",
+                k
+            );
+            assert_eq!(map.clone().remove(k).is_some(), present, "{}", k);
+        }
+        *map.get_mut("key2").unwrap() += 1;
+        assert_eq!(map.get("key2"), Some(&21));
+    }
+
+    #[test]
+    fn test_get_index_of_borrowed() {
+        let mut map: RegistOrderMap<String, i32> = RegistOrderMap::new();
+        map.insert("key2".to_string(), 20);
+        map.insert("key1".to_string(), 10);
+        assert_eq!(map.get_index_of("key1"), Some(1));
+        assert_eq!(map.get_index_of("key2"), Some(0));
+        assert_eq!(map.get_index_of("key3"), None);
+        assert_eq!(map.get_index_of(&"key1".to_string()), Some(1));
+    }
+
+    #[test]
+    fn test_try_index() {
+        let mut map = RegistOrderMap::new();
+        map.insert("key1".to_string(), 10);
+        assert_eq!(map.try_index("key1"), Ok(&10));
+        let err = map.try_index("key2").unwrap_err();
+        assert_eq!(err.key(), r#""key2""#);
+        assert_eq!(err.to_string(), r#"key not found: "key2""#);
+    }
+
+    #[test]
+    fn test_try_from_vec() {
+        let map = RegistOrderMap::try_from(vec![("key2", 20), ("key1", 10), ("key3", 30)]).unwrap();
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (*k, *v)).collect();
+        assert_eq!(pairs, [("key2", 20), ("key1", 10), ("key3", 30)]);
+        assert!(RegistOrderMap::<&str, i32>::try_from(Vec::new())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_try_from_vec_duplicate() {
+        let pairs = vec![
+            ("key1", 10),
+            ("key2", 20),
+            ("key3", 30),
+            ("key2", 21),
+            ("key1", 11),
+        ];
+        let err = RegistOrderMap::try_from(pairs).unwrap_err();
+        assert_eq!(err, DuplicateKey { index: 3 });
+        assert_eq!(err.index(), 3);
+        assert_eq!(err.to_string(), "duplicate key at index 3");
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        assert_eq!(map.pop_front(), Some(("key1", 10)));
+        assert_eq!(map.pop(), Some(("key3", 30)));
+        assert_eq!(map.pop(), Some(("key2", 20)));
+        assert_eq!(map.pop(), None);
+        assert_eq!(map.pop_front(), None);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let mut map: RegistOrderMap<String, i32> =
+            (0..6).map(|i| (format!("key{}", i), i)).collect();
+        let capacity = map.capacity();
+        map.truncate(10);
+        assert_eq!(map.len(), 6);
+        map.truncate(3);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.capacity(), capacity);
+        for i in 0..6 {
+            let expected = if i < 3 { Some(&i) } else { None };
+            assert_eq!(map.get(format!("key{}", i).as_str()), expected);
+        }
+        map.insert("key5".to_string(), 50);
+        assert_eq!(map.get_index_of("key5"), Some(3));
+        map.truncate(0);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_remove_range() {
+        let mut map: RegistOrderMap<_, _> = (0..6).map(|i| (i, i * 10)).collect();
+        assert_eq!(map.remove_range(2..4), [(2, 20), (3, 30)]);
+        assert_eq!(map.as_pairs(), &[(0, 0), (1, 10), (4, 40), (5, 50)]);
+        assert_eq!(map.get(&4), Some(&40));
+        assert_eq!(map.remove_range(3..), [(5, 50)]);
+        assert!(map.remove_range(1..1).is_empty());
+        assert_eq!(map.remove_range(..), [(0, 0), (1, 10), (4, 40)]);
+        assert!(map.is_empty());
     }
 
-    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
-    where
-        M: MapAccess<'de>,
-    {
-        let mut map = RegistOrderMap::with_capacity(access.size_hint().unwrap_or(0));
+    #[test]
+    fn test_remove_first_last() {
+        let mut map =
+            RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30), ("key4", 40)]);
+        assert_eq!(map.remove_first(), Some(("key1", 10)));
+        assert_eq!(map.remove_last(), Some(("key4", 40)));
+        let keys: Vec<&str> = map.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, ["key2", "key3"]);
+        assert_eq!(map.remove_first(), Some(("key2", 20)));
+        assert_eq!(map.remove_last(), Some(("key3", 30)));
+        assert_eq!(map.remove_first(), None);
+        assert_eq!(map.remove_last(), None);
+    }
 
-        while let Some((key, value)) = access.next_entry()? {
-            map.insert(key, value);
+    #[cfg(feature = "auto-shrink")]
+    #[test]
+    fn test_auto_shrink() {
+        let mut map: RegistOrderMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        let capacity = map.capacity();
+        for i in 0..90 {
+            map.remove(&i);
         }
+        assert!(map.capacity() < capacity / 4);
+        assert!(map.capacity() >= map.len());
+        map.pop_front();
+        map.pop();
+        assert!(map.capacity() >= map.len());
+        assert_eq!(map.get(&95), Some(&95));
 
-        Ok(map)
+        let mut map: RegistOrderMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        map.truncate(25);
+        let capacity = map.capacity();
+        match map.entry(0) {
+            Entry::Occupied(e) => assert!(matches!(
+                e.replace_entry_with(|_, _| None),
+                Entry::Vacant(_)
+            )),
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert!(map.capacity() < capacity);
+        assert!(map.capacity() >= map.len());
     }
-}
 
-#[cfg(feature = "serde")]
-impl<'de, K, V> Deserialize<'de> for RegistOrderMap<K, V>
-where
-    K: Deserialize<'de> + Eq,
-    V: Deserialize<'de>,
-{
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        // Instantiate our Visitor and ask the Deserializer to drive
-        // it over the input data, resulting in an instance of MyMap.
-        deserializer.deserialize_map(RegistOrderMapVisitor::new())
+    #[cfg(not(feature = "auto-shrink"))]
+    #[test]
+    fn test_auto_shrink() {
+        let mut map: RegistOrderMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+        let capacity = map.capacity();
+        for i in 0..90 {
+            map.remove(&i);
+        }
+        map.pop_front();
+        map.pop();
+        assert_eq!(map.capacity(), capacity);
     }
 
-}
+    #[test]
+    fn test_remove_full() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        let index = map.get_index_of(&"key2");
+        assert_eq!(index, Some(1));
+        assert_eq!(map.remove_full(&"key2"), Some((1, "key2", 20)));
+        assert_eq!(map.remove_full(&"key2"), None);
+        assert_eq!(map.get_index_of(&"key3"), Some(1));
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    #[cfg(feature = "serde")]
-    use serde_json;
+    #[test]
+    fn test_remove_if() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        assert_eq!(map.remove_if(&"key2", |v| *v == 20), Some(20));
+        assert_eq!(map.remove_if(&"key4", |_| true), None);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key1", &10)));
+        assert_eq!(iter.next(), Some((&"key3", &30)));
+        assert_eq!(iter.next(), None);
+    }
 
     #[test]
-    fn test_insert() {
-        let key1 = "key1".to_string();
-        let key2 = "key2".to_string();
+    fn test_remove_if_retained() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20)]);
+        assert_eq!(map.remove_if(&"key1", |v| *v > 10), None);
+        assert_eq!(map.get(&"key1"), Some(&10));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn test_increment() {
+        let mut map: RegistOrderMap<&str, u32> = RegistOrderMap::new();
+        for word in ["b", "a", "b", "c", "b", "a"] {
+            map.increment(word);
+        }
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"b", &3)));
+        assert_eq!(iter.next(), Some((&"a", &2)));
+        assert_eq!(iter.next(), Some((&"c", &1)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_values_mut_range() {
+        let mut map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 3), ("key4", 4)]);
+        for v in map.values_mut_range(1..3) {
+            *v *= 10;
+        }
+        let values: Vec<i32> = map.iter().map(|(_, v)| *v).collect();
+        assert_eq!(values, [1, 20, 30, 4]);
+        assert_eq!(map.values_mut_range(3..).count(), 1);
+        assert_eq!(map.values_mut_range(..=1).count(), 2);
+        assert_eq!(map.values_mut_range(2..10).count(), 2);
+        assert_eq!(map.values_mut_range(5..8).count(), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = map.values_mut_range(3..1).count();
+        assert_eq!(reversed, 0);
+    }
+
+    // Positions are derived from the entries on every lookup, so they cannot go stale. This pins
+    // that down for operations that move entries, should an index ever be kept alongside them.
+    #[test]
+    fn test_index_of_after_mixed_ops() {
+        let mut next = xorshift64(0x9e37_79b9_7f4a_7c15);
         let mut map = RegistOrderMap::new();
-        map.insert(key2.clone(), 20);
-        assert_eq!(map.get(&key1), None);
-        assert_eq!(map.get(&key2), Some(&20));
-        map.insert(key1.clone(), 10);
-        assert_eq!(map.get(&key1), Some(&10));
-        assert_eq!(map.get(&key2), Some(&20));
+        for step in 0..5_000u64 {
+            let k = next() % 48;
+            match next() % 6 {
+                0 | 1 => map.insert(k, step),
+                2 => {
+                    map.remove(&k);
+                }
+                3 => {
+                    let index = (next() as usize) % (map.len() + 1);
+                    if map.get(&k).is_some() && index == map.len() {
+                        continue;
+                    }
+                    map.shift_insert(index, k, step);
+                }
+                4 if !map.is_empty() => {
+                    let from = (next() as usize) % map.len();
+                    let to = (next() as usize) % map.len();
+                    map.move_index(from, to);
+                }
+                _ => {
+                    let modulus = next() % 4 + 2;
+                    map.retain(|k, _| k % modulus != 0);
+                }
+            }
+            for (i, (k, _)) in map.iter().enumerate() {
+                assert_eq!(map.get_index_of(k), Some(i), "step {step}");
+            }
+            for k in 0..48 {
+                if let Some(i) = map.get_index_of(&k) {
+                    assert_eq!(map.get_index(i).map(|(k, _)| *k), Some(k));
+                }
+            }
+        }
     }
 
     #[test]
@@ -238,6 +3035,288 @@ mod tests {
         assert_eq!(iter.next(), Some((&key1, &10)));
     }
 
+    #[test]
+    fn test_chunks() {
+        let map = RegistOrderMap::from([
+            ("key1", 1),
+            ("key2", 2),
+            ("key3", 3),
+            ("key4", 4),
+            ("key5", 5),
+        ]);
+        let sizes: Vec<usize> = map.chunks(2).map(|chunk| chunk.count()).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+        let mut chunks = map.chunks(2);
+        chunks.next();
+        let mut chunk = chunks.next().unwrap();
+        assert_eq!(chunk.next(), Some((&"key3", &3)));
+        assert_eq!(chunk.next(), Some((&"key4", &4)));
+        assert_eq!(chunk.next(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_chunks_zero() {
+        let map = RegistOrderMap::from([("key1", 1)]);
+        map.chunks(0);
+    }
+
+    #[test]
+    fn test_windows() {
+        let map = RegistOrderMap::from([("key1", 1), ("key2", 2), ("key3", 4), ("key4", 8)]);
+        let deltas: Vec<i32> = map
+            .windows(2)
+            .map(|mut window| {
+                let (_, a) = window.next().unwrap();
+                let (_, b) = window.next().unwrap();
+                b - a
+            })
+            .collect();
+        assert_eq!(deltas, [1, 2, 4]);
+        assert_eq!(map.windows(5).count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_windows_zero() {
+        let map = RegistOrderMap::from([("key1", 1)]);
+        map.windows(0);
+    }
+
+    #[test]
+    fn test_indexed_iter() {
+        let map = RegistOrderMap::from([("key2", 20), ("key1", 10), ("key3", 30)]);
+        let indexed: Vec<(usize, &&str, &i32)> = map.indexed_iter().collect();
+        assert_eq!(
+            indexed,
+            [(0, &"key2", &20), (1, &"key1", &10), (2, &"key3", &30)]
+        );
+        assert!(map.indexed_iter().map(|(i, _, _)| i).eq(0..map.len()));
+    }
+
+    #[test]
+    fn test_rev_iter() {
+        let map = RegistOrderMap::from([("key2", 20), ("key1", 10), ("key3", 30)]);
+        let mut iter = map.rev_iter();
+        assert_eq!(iter.next(), Some((&"key3", &30)));
+        assert_eq!(iter.next(), Some((&"key1", &10)));
+        assert_eq!(iter.next(), Some((&"key2", &20)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_into_iter_rev() {
+        let mut map = RegistOrderMap::new();
+        map.insert("key2".to_string(), 20);
+        map.insert("key1".to_string(), 10);
+        map.insert("key3".to_string(), 30);
+        let pairs: Vec<(String, i32)> = map.clone().into_iter().rev().collect();
+        assert_eq!(
+            pairs,
+            [
+                ("key3".to_string(), 30),
+                ("key1".to_string(), 10),
+                ("key2".to_string(), 20)
+            ]
+        );
+        let mut iter = map.into_iter();
+        assert_eq!(iter.next(), Some(("key2".to_string(), 20)));
+        assert_eq!(iter.next_back(), Some(("key3".to_string(), 30)));
+        assert_eq!(iter.next(), Some(("key1".to_string(), 10)));
+        assert_eq!(iter.next_back(), None);
+    }
+
+    #[test]
+    fn test_into_keys_values() {
+        fn assert_traits<I: ExactSizeIterator + DoubleEndedIterator + FusedIterator>(_: &I) {}
+
+        let map = RegistOrderMap::from([("key2", 20), ("key1", 10), ("key3", 30)]);
+        let mut values = map.clone().into_values();
+        assert_traits(&values);
+        assert_eq!(values.len(), 3);
+        assert_eq!(values.next(), Some(20));
+        assert_eq!(values.len(), 2);
+        assert_eq!(values.next_back(), Some(30));
+        assert_eq!(values.next(), Some(10));
+        assert_eq!(values.next(), None);
+        assert_eq!(values.next(), None);
+
+        let keys = map.clone().into_keys();
+        assert_traits(&keys);
+        assert_eq!(keys.rev().collect::<Vec<_>>(), ["key3", "key1", "key2"]);
+        assert_traits(&map.into_iter());
+    }
+
+    #[test]
+    fn test_len_consistent_after_mutation() {
+        type Op = fn(&mut RegistOrderMap<i32, i32>);
+        let ops: &[(&str, Op)] = &[
+            ("insert new", |m| m.insert(10, 10)),
+            ("insert existing", |m| m.insert(1, 10)),
+            ("shift_insert", |m| {
+                m.shift_insert(0, 10, 10);
+            }),
+            ("insert_or_get", |m| {
+                m.insert_or_get(10, 10);
+            }),
+            ("insert_many", |m| {
+                m.insert_many([(1, 1), (10, 10), (11, 11)])
+            }),
+            ("upsert_many", |m| {
+                m.upsert_many([(1, 1), (10, 10)]);
+            }),
+            ("get_full_or_insert_with", |m| {
+                m.get_full_or_insert_with(10, || 10);
+            }),
+            ("get_or_insert_default", |m| {
+                m.get_or_insert_default(10);
+            }),
+            ("increment", |m| m.increment(10)),
+            ("entry", |m| {
+                m.entry(10).or_insert(10);
+            }),
+            ("entry_ref", |m| {
+                m.entry_ref(&10).or_insert(10);
+            }),
+            ("extend", |m| m.extend([(1, 1), (10, 10)])),
+            ("remove", |m| {
+                m.remove(&2);
+            }),
+            ("remove missing", |m| {
+                m.remove(&10);
+            }),
+            ("remove_full", |m| {
+                m.remove_full(&2);
+            }),
+            ("remove_if", |m| {
+                m.remove_if(&2, |_| true);
+            }),
+            ("pop_front", |m| {
+                m.pop_front();
+            }),
+            ("pop", |m| {
+                m.pop();
+            }),
+            ("remove_range", |m| {
+                m.remove_range(1..3);
+            }),
+            ("truncate", |m| m.truncate(2)),
+            ("move_index", |m| m.move_index(0, 4)),
+            ("move_to_front", |m| {
+                m.move_to_front(&3);
+            }),
+            ("retain", |m| m.retain(|_, v| *v % 2 == 0)),
+            ("try_retain", |m| {
+                let _ = m.try_retain(|k, _| if *k < 3 { Ok(true) } else { Err(()) });
+            }),
+            ("retain_count", |m| {
+                m.retain_count(|k, _| *k != 0);
+            }),
+            ("drain_filter_ordered", |m| {
+                m.drain_filter_ordered(|k, _| *k % 2 == 1).next();
+            }),
+            ("sort_by", |m| m.sort_by(|a, _, b, _| b.cmp(a))),
+            ("clear", |m| m.clear()),
+            ("shrink_to", |m| m.shrink_to(0)),
+        ];
+        for (name, op) in ops {
+            let mut map: RegistOrderMap<i32, i32> = (0..5).map(|i| (i, i)).collect();
+            op(&mut map);
+            assert_eq!(map.len(), map.iter().count(), "{}", name);
+            assert_eq!(map.is_empty(), map.iter().next().is_none(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_from_iter_reserves() {
+        let (count, map) = allocations(|| {
+            (0..10_000)
+                .map(|i| (i, i))
+                .collect::<RegistOrderMap<_, _>>()
+        });
+        assert_eq!(map.len(), 10_000);
+        assert_eq!(count, 1);
+
+        // Without a usable size hint the storage still grows geometrically.
+        let (count, map) = allocations(|| {
+            (0..20_000)
+                .filter(|i| i % 2 == 0)
+                .map(|i| (i, i))
+                .collect::<RegistOrderMap<_, _>>()
+        });
+        assert_eq!(map.len(), 10_000);
+        assert!(count <= 16, "{count} allocations");
+
+        let mut map = RegistOrderMap::from([(-1, -1)]);
+        let (count, ()) = allocations(|| map.extend((0..10_000).map(|i| (i, i))));
+        assert_eq!(map.len(), 10_001);
+        assert_eq!(count, 1);
+        assert_eq!(map.get_index(0), Some((&-1, &-1)));
+        assert_eq!(map.get_index(10_000), Some((&9_999, &9_999)));
+    }
+
+    #[test]
+    fn test_extend_overwrites() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20)]);
+        map.extend([("key3", 30), ("key1", 11)]);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key1", &11)));
+        assert_eq!(iter.next(), Some((&"key2", &20)));
+        assert_eq!(iter.next(), Some((&"key3", &30)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_extend_ref() {
+        let mut map = RegistOrderMap::from([("key1", 10), ("key2", 20)]);
+        let other = RegistOrderMap::from([("key3", 30), ("key1", 11)]);
+        map.extend(other.iter());
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key1", &11)));
+        assert_eq!(iter.next(), Some((&"key2", &20)));
+        assert_eq!(iter.next(), Some((&"key3", &30)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_eq_len_mismatch() {
+        let large = counted_map(10_000);
+        let small = counted_map(1);
+        let (count, ()) = comparisons(|| {
+            assert!(large != small);
+            assert!(small != large);
+        });
+        assert_eq!(count, 0);
+        let (count, equal) = comparisons(|| large == large);
+        assert!(equal);
+        assert_eq!(count, 10_000);
+    }
+
+    #[test]
+    fn test_clone_linear() {
+        let map = counted_map(10_000);
+        let (count, cloned) = comparisons(|| map.clone());
+        // Rebuilding through `insert` would compare keys about 50 million times.
+        assert_eq!(count, 0);
+        assert_eq!(cloned.len(), 10_000);
+        assert!(cloned
+            .iter()
+            .map(|(k, v)| (k.0, *v))
+            .eq((0..10_000).map(|i| (i, i))));
+    }
+
+    #[test]
+    fn test_iter_as_slice() {
+        let map = RegistOrderMap::from([("key1", 10), ("key2", 20), ("key3", 30)]);
+        let mut iter = map.iter();
+        assert_eq!(iter.as_slice(), &[("key1", 10), ("key2", 20), ("key3", 30)]);
+        iter.next();
+        assert_eq!(iter.as_slice(), &[("key2", 20), ("key3", 30)]);
+        iter.next();
+        iter.next();
+        assert!(iter.as_slice().is_empty());
+    }
+
     #[test]
     fn test_from() {
         let map = RegistOrderMap::from([("key2", 20), ("key1", 10)]);
@@ -246,6 +3325,84 @@ mod tests {
         assert_eq!(iter.next(), Some((&"key1", &10)));
     }
 
+    #[test]
+    fn test_find_map() {
+        let map = RegistOrderMap::from([("key1", 10), ("key2", 30), ("key3", 40), ("key4", 5)]);
+        let first = map.find_map(|k, v| (v * 2 > 50).then_some((*k, v * 2)));
+        assert_eq!(first, Some(("key2", 60)));
+        assert_eq!(map.find_map(|_, v| (*v > 100).then_some(*v)), None);
+    }
+
+    #[test]
+    fn test_values_aggregates() {
+        let map = RegistOrderMap::from([("key2", 20), ("key1", 10), ("key3", 30), ("key4", 5)]);
+        assert_eq!(map.values().collect::<Vec<_>>(), [&20, &10, &30, &5]);
+        assert_eq!(map.sum_values::<i32>(), 65);
+        assert_eq!(map.max_value(), Some(&30));
+        let map = RegistOrderMap::from([("a", 1.5f64), ("b", 2.25)]);
+        assert_eq!(map.sum_values::<f64>(), 3.75);
+        let empty: RegistOrderMap<&str, u8> = RegistOrderMap::new();
+        assert_eq!(empty.sum_values::<u8>(), 0);
+        assert_eq!(empty.max_value(), None);
+    }
+
+    #[test]
+    fn test_from_std_maps() {
+        let btree: std::collections::BTreeMap<_, _> = [("key3", 30), ("key1", 10), ("key2", 20)]
+            .into_iter()
+            .collect();
+        let map = RegistOrderMap::from(btree);
+        assert_eq!(map.as_pairs(), &[("key1", 10), ("key2", 20), ("key3", 30)]);
+
+        let hash: std::collections::HashMap<_, _> = [("key3", 30), ("key1", 10), ("key2", 20)]
+            .into_iter()
+            .collect();
+        let expected: Vec<_> = hash.iter().map(|(k, v)| (*k, *v)).collect();
+        let map = RegistOrderMap::from(hash);
+        assert_eq!(map.as_pairs(), expected.as_slice());
+        assert_eq!(map.get(&"key2"), Some(&20));
+    }
+
+    #[test]
+    fn test_from_capacity() {
+        let pairs: [(u32, u32); 16] = std::array::from_fn(|i| (i as u32, i as u32));
+        let (count, map) = allocations(|| RegistOrderMap::from(pairs));
+        assert_eq!(count, 1);
+        assert_eq!(map.len(), 16);
+        assert!(map.capacity() >= 16);
+        assert_eq!(map.get_index(15), Some((&15, &15)));
+    }
+
+    #[test]
+    fn test_from_duplicates() {
+        let map = RegistOrderMap::from([("key2", 20), ("key1", 10), ("key2", 21)]);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key2", &21)));
+        assert_eq!(iter.next(), Some((&"key1", &10)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_non_copy_values() {
+        let mut map = RegistOrderMap::new();
+        map.insert("key1".to_string(), vec![1u8]);
+        assert_eq!(map.get("key1"), Some(&vec![1]));
+
+        let map = RegistOrderMap::from([
+            ("key1".to_string(), vec![1u8]),
+            ("key2".to_string(), vec![2]),
+        ]);
+        assert_eq!(map.get("key2"), Some(&vec![2]));
+
+        let mut map: RegistOrderMap<String, Vec<u8>> =
+            (1..=2u8).map(|i| (format!("key{i}"), vec![i])).collect();
+        assert_eq!(map.get("key1"), Some(&vec![1]));
+
+        map.extend([("key3".to_string(), vec![3u8])]);
+        assert_eq!(map.get("key3"), Some(&vec![3]));
+        assert_eq!(map.len(), 3);
+    }
+
     #[test]
     fn test_debug() {
         let key1 = "key1".to_string();
@@ -257,6 +3414,11 @@ mod tests {
             format!("{:?}", map),
             r#"[Entry { key: "key2", val: 20 }, Entry { key: "key1", val: 10 }]"#
         );
+        assert_eq!(
+            format!("{:#?}", map),
+            "[\n    0 => \"key2\": 20,\n    1 => \"key1\": 10,\n]"
+        );
+        assert_eq!(format!("{:#?}", RegistOrderMap::<i32, i32>::new()), "[]");
     }
 
     #[cfg(feature = "serde")]
@@ -282,4 +3444,318 @@ mod tests {
         assert_eq!(iter.next(), Some((&key2, &20)));
         assert_eq!(iter.next(), Some((&key1, &10)));
     }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_to_value() {
+        let map = RegistOrderMap::from([("key2", 20), ("key3", 30), ("key1", 10)]);
+        let value = map.to_value().unwrap();
+        let keys: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        assert_eq!(keys, ["key2", "key3", "key1"]);
+        assert_eq!(value.to_string(), r#"{"key2":20,"key3":30,"key1":10}"#);
+    }
+
+    #[cfg(all(feature = "json", not(feature = "preserve_order")))]
+    #[test]
+    fn test_to_value() {
+        let map = RegistOrderMap::from([("key2", 20), ("key3", 30), ("key1", 10)]);
+        let value = map.to_value().unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"[["key2",20],["key3",30],["key1",10]]"#
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_sorted_serialize() {
+        let map = RegistOrderMap::from([("key2", 20), ("key3", 30), ("key1", 10)]);
+        let json_str = serde_json::to_string(&SortedSerialize(&map)).unwrap();
+        assert_eq!(json_str, r#"{"key1":10,"key2":20,"key3":30}"#);
+        let json_str = serde_json::to_string(&map).unwrap();
+        assert_eq!(json_str, r#"{"key2":20,"key3":30,"key1":10}"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_skip_none() {
+        let map = RegistOrderMap::from([("key2", Some(20)), ("key3", None), ("key1", Some(10))]);
+        let json_str = serde_json::to_string(&SkipNone(&map)).unwrap();
+        assert_eq!(json_str, r#"{"key2":20,"key1":10}"#);
+        let json_str = serde_json::to_string(&map).unwrap();
+        assert_eq!(json_str, r#"{"key2":20,"key3":null,"key1":10}"#);
+        let map: RegistOrderMap<&str, Option<i32>> = RegistOrderMap::from([("key1", None)]);
+        assert_eq!(serde_json::to_string(&SkipNone(&map)).unwrap(), "{}");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_ordered() {
+        let pairs: &[(&str, i32)] = &[("b", 2), ("a", 1)];
+        let json_str = serde_json::to_string(&SerializeOrdered(pairs)).unwrap();
+        assert_eq!(json_str, r#"{"b":2,"a":1}"#);
+        let pairs: &[(&str, i32)] = &[("a", 1), ("b", 2)];
+        let json_str = serde_json::to_string(&SerializeOrdered(pairs)).unwrap();
+        assert_eq!(json_str, r#"{"a":1,"b":2}"#);
+        let map = RegistOrderMap::from([("a", 1), ("b", 2)]);
+        assert_eq!(serde_json::to_string(&&map).unwrap(), json_str);
+        assert_eq!(
+            serde_json::to_string(&SerializeOrdered(map.as_pairs())).unwrap(),
+            json_str
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_capacity() {
+        let object: serde_json::Map<String, serde_json::Value> = (0..2_000)
+            .map(|i| (format!("key{}", i), i.into()))
+            .collect();
+
+        // `serde_json::Value` reports the length of the object, so the map is allocated once.
+        let map: RegistOrderMap<String, i64> =
+            serde_json::from_value(serde_json::Value::Object(object.clone())).unwrap();
+        assert_eq!(map.len(), 2_000);
+        assert_eq!(map.capacity(), 2_000);
+
+        // Parsing text gives no size hint, so the storage grows geometrically instead.
+        let text = serde_json::to_string(&object).unwrap();
+        let map: RegistOrderMap<String, i64> = serde_json::from_str(&text).unwrap();
+        assert_eq!(map.len(), 2_000);
+        assert!(map.capacity() < 4_000);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_borrowed() {
+        let json_str = String::from(r#"{"key2":20,"key1":10}"#);
+        let map: RegistOrderMap<&str, i64> = serde_json::from_str(&json_str).unwrap();
+        let input = json_str.as_bytes().as_ptr_range();
+        let mut iter = map.iter();
+        let (key, val) = iter.next().unwrap();
+        assert_eq!((*key, *val), ("key2", 20));
+        assert!(input.contains(&key.as_ptr()));
+        let (key, val) = iter.next().unwrap();
+        assert_eq!((*key, *val), ("key1", 10));
+        assert!(input.contains(&key.as_ptr()));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_with() {
+        #[derive(serde_derive::Deserialize)]
+        struct Config {
+            name: String,
+            #[serde(deserialize_with = "crate::deserialize_regist_order_map")]
+            limits: RegistOrderMap<String, u32>,
+            #[serde(default, deserialize_with = "crate::deserialize_regist_order_map")]
+            extra: RegistOrderMap<String, u32>,
+        }
+
+        let config: Config =
+            serde_json::from_str(r#"{"limits":{"zeta":1,"alpha":2,"mu":3},"name":"app"}"#).unwrap();
+        assert_eq!(config.name, "app");
+        let keys: Vec<&str> = config.limits.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["zeta", "alpha", "mu"]);
+        assert!(config.extra.is_empty());
+        assert!(serde_json::from_str::<Config>(r#"{"name":"app","limits":[1]}"#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+        Group(RegistOrderMap<String, u32>),
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_round_trip_enum_values() {
+        let mut group = RegistOrderMap::new();
+        group.insert("b".to_string(), 2);
+        group.insert("a".to_string(), 1);
+        let mut map = RegistOrderMap::new();
+        map.insert("rect".to_string(), Shape::Rect { w: 2, h: 3 });
+        map.insert("group".to_string(), Shape::Group(group));
+        map.insert("empty".to_string(), Shape::Empty);
+        map.insert("circle".to_string(), Shape::Circle(1.5));
+        let json_str = serde_json::to_string(&map).unwrap();
+        assert_eq!(
+            json_str,
+            r#"{"rect":{"Rect":{"w":2,"h":3}},"group":{"Group":{"b":2,"a":1}},"empty":"Empty","circle":{"Circle":1.5}}"#
+        );
+        let map: RegistOrderMap<String, Shape> = serde_json::from_str(&json_str).unwrap();
+        let keys: Vec<&str> = map.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["rect", "group", "empty", "circle"]);
+        assert!(matches!(map.get("rect"), Some(Shape::Rect { w: 2, h: 3 })));
+        assert!(matches!(map.get("empty"), Some(Shape::Empty)));
+        assert!(matches!(map.get("circle"), Some(Shape::Circle(r)) if *r == 1.5));
+        match map.get("group") {
+            Some(Shape::Group(group)) => {
+                assert_eq!(
+                    group.as_pairs(),
+                    &[("b".to_string(), 2), ("a".to_string(), 1)]
+                );
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn ordered_map() -> RegistOrderMap<String, i64> {
+        let mut map = RegistOrderMap::new();
+        map.insert("zeta".to_string(), 1);
+        map.insert("alpha".to_string(), 2);
+        map.insert("mu".to_string(), 3);
+        map
+    }
+
+    #[cfg(feature = "serde")]
+    fn assert_ordered(map: &RegistOrderMap<String, i64>) {
+        let keys: Vec<&str> = map.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["zeta", "alpha", "mu"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_round_trip_json() {
+        let text = serde_json::to_string(&ordered_map()).unwrap();
+        assert_eq!(text, r#"{"zeta":1,"alpha":2,"mu":3}"#);
+        let map: RegistOrderMap<String, i64> = serde_json::from_str(&text).unwrap();
+        assert_ordered(&map);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_round_trip_json_idempotent() {
+        let mut map = RegistOrderMap::new();
+        map.insert("zz top".to_string(), 1);
+        map.insert("Ärger".to_string(), 2);
+        map.insert("a\"quoted\"".to_string(), 3);
+        map.insert("10".to_string(), 4);
+        map.insert("".to_string(), 5);
+        let first = serde_json::to_string(&map).unwrap();
+        let map: RegistOrderMap<String, i64> = serde_json::from_str(&first).unwrap();
+        let second = serde_json::to_string(&map).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            first,
+            r#"{"zz top":1,"Ärger":2,"a\"quoted\"":3,"10":4,"":5}"#
+        );
+        // Going through `serde_json::Value` only keeps the order with its `preserve_order` feature.
+        #[cfg(feature = "preserve_order")]
+        {
+            let value: serde_json::Value = serde_json::from_str(&first).unwrap();
+            assert_eq!(serde_json::to_string(&value).unwrap(), first);
+            assert_eq!(map.to_value().unwrap(), value);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_nested_values() {
+        fn nested(depth: usize, i: usize) -> serde_json::Value {
+            if depth == 0 {
+                return serde_json::json!([i, "leaf", null, true]);
+            }
+            serde_json::json!({
+                "depth": depth,
+                "items": [0, 1, 2, 3],
+                "child": nested(depth - 1, i),
+            })
+        }
+
+        let keys: Vec<String> = (0..200).map(|i| format!("k{}", (i * 37) % 200)).collect();
+        let mut text = String::from("{");
+        for (i, k) in keys.iter().enumerate() {
+            if i > 0 {
+                text.push(',');
+            }
+            text.push_str(&format!("{:?}:{}", k, nested(32, i)));
+        }
+        text.push('}');
+
+        let map: RegistOrderMap<String, serde_json::Value> = serde_json::from_str(&text).unwrap();
+        assert!(map.iter().map(|(k, _)| k).eq(keys.iter()));
+        for (i, (_, v)) in map.iter().enumerate() {
+            assert_eq!(*v, nested(32, i));
+        }
+    }
+
+    #[test]
+    fn test_from_hash_map_sorted() {
+        let hash: std::collections::HashMap<String, i32> =
+            (0..50).map(|i| (format!("key{:02}", 49 - i), i)).collect();
+        let map = RegistOrderMap::from_hash_map_sorted(hash.clone());
+        let keys: Vec<&str> = map.iter().map(|(k, _)| k.as_str()).collect();
+        let expected: Vec<String> = (0..50).map(|i| format!("key{:02}", i)).collect();
+        assert_eq!(keys, expected);
+        assert_eq!(map.get("key49"), Some(&0));
+
+        // The order from `From<HashMap>` is whatever the `HashMap` yields; sorting makes it stable.
+        let mut unsorted = RegistOrderMap::from(hash);
+        unsorted.sort_keys();
+        assert_eq!(unsorted, map);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_hash_map_sorted_json() {
+        let hash: std::collections::HashMap<String, i32> =
+            (0..50).map(|i| (format!("key{:02}", 49 - i), i)).collect();
+        let mut unsorted = RegistOrderMap::from(hash.clone());
+        unsorted.sort_keys();
+        assert_eq!(
+            serde_json::to_string(&unsorted).unwrap(),
+            serde_json::to_string(&RegistOrderMap::from_hash_map_sorted(hash)).unwrap()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_round_trip_yaml() {
+        let text = serde_yaml::to_string(&ordered_map()).unwrap();
+        assert_eq!(text, "zeta: 1\nalpha: 2\nmu: 3\n");
+        let map: RegistOrderMap<String, i64> = serde_yaml::from_str(&text).unwrap();
+        assert_ordered(&map);
+    }
+
+    // Requires the `preserve_order` feature of `toml`, otherwise tables come back sorted by key.
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_round_trip_toml() {
+        let text = toml::to_string(&ordered_map()).unwrap();
+        assert_eq!(text, "zeta = 1\nalpha = 2\nmu = 3\n");
+        let map: RegistOrderMap<String, i64> = toml::from_str(&text).unwrap();
+        assert_ordered(&map);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_round_trip_json_nested() {
+        let mut map = RegistOrderMap::new();
+        map.insert("outer2".to_string(), ordered_map());
+        map.insert("outer1".to_string(), RegistOrderMap::new());
+        map.insert("outer3".to_string(), ordered_map());
+        let text = serde_json::to_string(&map).unwrap();
+        assert_eq!(
+            text,
+            r#"{"outer2":{"zeta":1,"alpha":2,"mu":3},"outer1":{},"outer3":{"zeta":1,"alpha":2,"mu":3}}"#
+        );
+        let map: RegistOrderMap<String, RegistOrderMap<String, i64>> =
+            serde_json::from_str(&text).unwrap();
+        let keys: Vec<&str> = map.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["outer2", "outer1", "outer3"]);
+        assert_ordered(map.get("outer2").unwrap());
+        assert!(map.get("outer1").unwrap().is_empty());
+        assert_ordered(map.get("outer3").unwrap());
+    }
 }
@@ -0,0 +1,120 @@
+use crate::{Bucket, Iter};
+use std::borrow::Borrow;
+
+/// An `RegistOrderMultiMap` is like a `RegistOrderMap`, but it allows a key to occur more than once.
+/// Every insertion is appended, so all entries, including those sharing a key,
+/// are kept in the order of registration.
+#[derive(Clone)]
+pub struct RegistOrderMultiMap<K, V> {
+    entries: Vec<Bucket<K, V>>,
+}
+
+impl<K, V> RegistOrderMultiMap<K, V> {
+    /// Creates an empty RegistOrderMultiMap.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Appends a key-value pair to the end of the map, even if the key is already present.
+    pub fn insert(&mut self, k: K, v: V) {
+        self.entries.push(Bucket { key: k, val: v });
+    }
+    /// An iterator visiting all values for the key in registration order.
+    pub fn get_all<'a, Q>(&'a self, k: &'a Q) -> impl Iterator<Item = &'a V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.entries
+            .iter()
+            .filter(move |e| e.key.borrow() == k)
+            .map(|e| &e.val)
+    }
+    /// Removes all entries for the key, keeping the order of the rest,
+    /// and returns their values in registration order.
+    pub fn remove_all<Q>(&mut self, k: &Q) -> Vec<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        // A single pass that compacts the kept entries in place, keeping the capacity.
+        self.entries
+            .extract_if(.., |e| e.key.borrow() == k)
+            .map(|e| e.val)
+            .collect()
+    }
+    /// An iterator visiting all key-value pairs in registration order.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: self.entries.iter(),
+        }
+    }
+    /// Returns the number of entries in the map, counting every value of a repeated key.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    /// Returns true if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<K, V> Default for RegistOrderMultiMap<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for RegistOrderMultiMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            entries: iter
+                .into_iter()
+                .map(|(key, val)| Bucket { key, val })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_get_all() {
+        let mut map = RegistOrderMultiMap::new();
+        map.insert("key1", 10);
+        map.insert("key2", 20);
+        map.insert("key1", 11);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get_all(&"key1").collect::<Vec<_>>(), [&10, &11]);
+        assert_eq!(map.get_all(&"key2").collect::<Vec<_>>(), [&20]);
+        assert_eq!(map.get_all(&"key3").next(), None);
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&"key1", &10)));
+        assert_eq!(iter.next(), Some((&"key2", &20)));
+        assert_eq!(iter.next(), Some((&"key1", &11)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_remove_all() {
+        let mut map: RegistOrderMultiMap<String, i32> = [("a", 1), ("b", 2), ("a", 3), ("c", 4)]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        let ptr = map.entries.as_ptr();
+        assert_eq!(map.remove_all("a"), [1, 3]);
+        assert!(map.remove_all("a").is_empty());
+        assert_eq!(map.entries.as_ptr(), ptr);
+        let keys: Vec<&str> = map.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["b", "c"]);
+        map.remove_all("b");
+        map.remove_all("c");
+        assert!(map.is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{Entry, Iter, RegistOrderMap};
+use std::borrow::Borrow;
+
+/// A `NormalizedMap` is a `RegistOrderMap` that passes every key through a normalizer before
+/// using it, e.g. to build a case-insensitive map by lowercasing string keys.
+///
+/// The normalizer is applied consistently on insertion and on lookup, so `get("KEY")` finds an
+/// entry inserted as `"key"`. As the normalizer takes an owned key, a lookup by a borrowed key
+/// first turns it into one with `ToOwned`, which may allocate.
+#[derive(Clone)]
+pub struct NormalizedMap<K, V> {
+    map: RegistOrderMap<K, V>,
+    normalizer: fn(K) -> K,
+}
+
+impl<K, V> NormalizedMap<K, V> {
+    /// Creates an empty `NormalizedMap` that normalizes every key with `f`.
+    pub fn with_key_normalizer(f: fn(K) -> K) -> Self {
+        Self {
+            map: RegistOrderMap::new(),
+            normalizer: f,
+        }
+    }
+    fn normalize<Q>(&self, k: &Q) -> K
+    where
+        Q: ToOwned<Owned = K> + ?Sized,
+    {
+        (self.normalizer)(k.to_owned())
+    }
+    /// Inserts a key-value pair into the map, normalizing the key first.
+    /// A new key is appended to the end; an existing key keeps its position and takes the value.
+    pub fn insert(&mut self, k: K, v: V)
+    where
+        K: Eq,
+    {
+        self.map.insert((self.normalizer)(k), v);
+    }
+    /// Returns a reference to the value corresponding to the normalized key.
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        self.map.get(self.normalize(k).borrow())
+    }
+    /// Returns a mutable reference to the value corresponding to the normalized key.
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let k = self.normalize(k);
+        self.map.get_mut(k.borrow())
+    }
+    /// Returns true if the map contains a value for the normalized key.
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        self.map.contains_key(self.normalize(k).borrow())
+    }
+    /// Removes the normalized key from the map, keeping the order of the rest,
+    /// and returns its value.
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let k = self.normalize(k);
+        self.map.remove(k.borrow())
+    }
+    /// Gets the entry for the normalized key for in-place manipulation.
+    pub fn entry(&mut self, k: K) -> Entry<'_, K, V>
+    where
+        K: Eq,
+    {
+        self.map.entry((self.normalizer)(k))
+    }
+    /// An iterator visiting all key-value pairs in registration order,
+    /// with the keys as they were stored, i.e. normalized.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        self.map.iter()
+    }
+    /// Returns the number of elements in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    /// Returns true if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// Returns the underlying map, whose keys are all normalized.
+    pub fn as_map(&self) -> &RegistOrderMap<K, V> {
+        &self.map
+    }
+    /// Consumes the `NormalizedMap` and returns the underlying map.
+    pub fn into_map(self) -> RegistOrderMap<K, V> {
+        self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_normalizer() {
+        fn lowercase(k: String) -> String {
+            k.to_lowercase()
+        }
+
+        let mut map = NormalizedMap::with_key_normalizer(lowercase);
+        map.insert("KEY".to_string(), 1);
+        assert_eq!(map.get("key"), Some(&1));
+        assert_eq!(map.get("KEY"), Some(&1));
+        assert!(map.contains_key("kEY"));
+
+        map.insert("Key".to_string(), 2);
+        map.insert("other".to_string(), 3);
+        assert_eq!(map.len(), 2);
+        assert!(matches!(map.entry("KEY".to_string()), Entry::Occupied(_)));
+        *map.entry("NEW".to_string()).or_insert(0) += 4;
+        *map.get_mut("New").unwrap() += 1;
+        let pairs: Vec<_> = map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(pairs, [("key", 2), ("other", 3), ("new", 5)]);
+
+        assert_eq!(map.remove("OTHER"), Some(3));
+        assert!(!map.contains_key("other"));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.into_map().get("key"), Some(&2));
+    }
+}
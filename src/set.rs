@@ -0,0 +1,307 @@
+use crate::RegistOrderMap;
+use std::borrow::Borrow;
+
+#[cfg(feature = "serde")]
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, Serializer};
+#[cfg(feature = "serde")]
+use std::marker::PhantomData;
+
+/// An `RegistOrderSet` is like a `std::collections::HashSet`,
+/// but it guarantees the order of registration.
+/// It is a `RegistOrderMap` whose values are `()`.
+#[derive(Clone)]
+pub struct RegistOrderSet<T> {
+    map: RegistOrderMap<T, ()>,
+}
+
+impl<T> RegistOrderSet<T> {
+    /// Creates an empty RegistOrderSet.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Creates an empty `RegistOrderSet` with at least the specified capacity.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            map: RegistOrderMap::with_capacity(capacity),
+        }
+    }
+    /// Adds a value to the end of the set.
+    /// Returns false if the value was already present, in which case its position is kept.
+    pub fn insert(&mut self, value: T) -> bool
+    where
+        T: Eq,
+    {
+        match self.map.entry(value) {
+            crate::Entry::Occupied(_) => false,
+            crate::Entry::Vacant(entry) => {
+                entry.insert(());
+                true
+            }
+        }
+    }
+    /// Returns true if the set contains the value.
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.map.contains_key(value)
+    }
+    /// Removes a value from the set, keeping the order of the rest.
+    /// Returns whether the value was present.
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.map.remove(value).is_some()
+    }
+    /// An iterator visiting all values in registration order.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            inner: self.map.iter(),
+        }
+    }
+    /// Returns the number of elements in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    /// Returns true if the set contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// Visits the values of `self` in registration order,
+    /// followed by the values of `other` that are not in `self`, in `other`'s order.
+    pub fn union<'a>(&'a self, other: &'a RegistOrderSet<T>) -> Union<'a, T> {
+        Union {
+            first: self.iter(),
+            second: other.iter(),
+            set: self,
+        }
+    }
+    /// Visits the values of `self` that are also in `other`, in `self`'s registration order.
+    pub fn intersection<'a>(&'a self, other: &'a RegistOrderSet<T>) -> Intersection<'a, T> {
+        Intersection {
+            iter: self.iter(),
+            other,
+        }
+    }
+}
+
+impl<T> Default for RegistOrderSet<T> {
+    fn default() -> Self {
+        Self {
+            map: RegistOrderMap::new(),
+        }
+    }
+}
+
+impl<T> FromIterator<T> for RegistOrderSet<T>
+where
+    T: Eq,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = RegistOrderSet::new();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
+impl<T: std::fmt::Debug + Eq> std::fmt::Debug for RegistOrderSet<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// With the `serde` feature, a set is serialized as a sequence of its values in registration order,
+/// e.g. a JSON array, rather than as a map with `()` values.
+#[cfg(feature = "serde")]
+impl<T> Serialize for RegistOrderSet<T>
+where
+    T: Serialize + Eq,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+struct RegistOrderSetVisitor<T> {
+    marker: PhantomData<fn() -> RegistOrderSet<T>>,
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> Visitor<'de> for RegistOrderSetVisitor<T>
+where
+    T: Deserialize<'de> + Eq,
+{
+    type Value = RegistOrderSet<T>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A>(self, mut access: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        // Capped like the map's visitor, so a bogus length can't force a huge allocation.
+        let max = 1024 * 1024 / std::mem::size_of::<T>().max(1);
+        let mut set = RegistOrderSet::with_capacity(access.size_hint().unwrap_or(0).min(max));
+        while let Some(value) = access.next_element()? {
+            set.insert(value);
+        }
+        Ok(set)
+    }
+}
+
+/// Deserializes a set from a sequence. A repeated value keeps its first position.
+#[cfg(feature = "serde")]
+impl<'de, T> Deserialize<'de> for RegistOrderSet<T>
+where
+    T: Deserialize<'de> + Eq,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(RegistOrderSetVisitor {
+            marker: PhantomData,
+        })
+    }
+}
+
+pub struct Iter<'a, T: 'a> {
+    inner: crate::Iter<'a, T, ()>,
+}
+
+impl<'a, T: 'a> Iterator for Iter<'a, T>
+where
+    T: Eq,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(value, _)| value)
+    }
+}
+
+pub struct Union<'a, T: 'a> {
+    first: Iter<'a, T>,
+    second: Iter<'a, T>,
+    set: &'a RegistOrderSet<T>,
+}
+
+impl<'a, T: 'a> Iterator for Union<'a, T>
+where
+    T: Eq,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.first.next() {
+            Some(value) => Some(value),
+            None => self.second.find(|value| !self.set.contains(value)),
+        }
+    }
+}
+
+pub struct Intersection<'a, T: 'a> {
+    iter: Iter<'a, T>,
+    other: &'a RegistOrderSet<T>,
+}
+
+impl<'a, T: 'a> Iterator for Intersection<'a, T>
+where
+    T: Eq,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.find(|value| self.other.contains(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert() {
+        let mut set = RegistOrderSet::new();
+        assert!(set.insert("key2"));
+        assert!(set.insert("key1"));
+        assert!(!set.insert("key2"));
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&"key1"));
+        assert!(!set.contains(&"key3"));
+        let mut iter = set.iter();
+        assert_eq!(iter.next(), Some(&"key2"));
+        assert_eq!(iter.next(), Some(&"key1"));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut set: RegistOrderSet<_> = ["key1", "key2", "key3"].into_iter().collect();
+        assert!(set.remove(&"key2"));
+        assert!(!set.remove(&"key2"));
+        let values: Vec<_> = set.iter().collect();
+        assert_eq!(values, [&"key1", &"key3"]);
+        set.remove(&"key1");
+        set.remove(&"key3");
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn test_union() {
+        let a: RegistOrderSet<_> = [3, 1, 2].into_iter().collect();
+        let b: RegistOrderSet<_> = [4, 2, 5, 3].into_iter().collect();
+        let union: Vec<_> = a.union(&b).collect();
+        assert_eq!(union, [&3, &1, &2, &4, &5]);
+    }
+
+    #[test]
+    fn test_intersection() {
+        let a: RegistOrderSet<_> = [3, 1, 2].into_iter().collect();
+        let b: RegistOrderSet<_> = [4, 2, 5, 3].into_iter().collect();
+        let intersection: Vec<_> = a.intersection(&b).collect();
+        assert_eq!(intersection, [&3, &2]);
+    }
+
+    #[test]
+    fn test_debug() {
+        let set: RegistOrderSet<_> = ["key2", "key1"].into_iter().collect();
+        assert_eq!(format!("{:?}", set), r#"{"key2", "key1"}"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_round_trip_json() {
+        let set: RegistOrderSet<String> = ["zeta", "alpha", "mu"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let text = serde_json::to_string(&set).unwrap();
+        assert_eq!(text, r#"["zeta","alpha","mu"]"#);
+        let set: RegistOrderSet<String> = serde_json::from_str(&text).unwrap();
+        let values: Vec<&str> = set.iter().map(String::as_str).collect();
+        assert_eq!(values, ["zeta", "alpha", "mu"]);
+
+        let set: RegistOrderSet<i32> = serde_json::from_str("[3, 1, 3, 2]").unwrap();
+        let values: Vec<_> = set.iter().collect();
+        assert_eq!(values, [&3, &1, &2]);
+        assert!(serde_json::from_str::<RegistOrderSet<i32>>(r#"{"a":1}"#).is_err());
+    }
+}
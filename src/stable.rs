@@ -0,0 +1,146 @@
+use crate::RegistOrderMap;
+use std::borrow::Borrow;
+
+/// An identifier for an entry of a `StableIndexMap` that does not change when other entries
+/// are removed or moved, unlike its position. It is never reused within a map,
+/// so an index of a removed entry keeps finding nothing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StableIndex(u64);
+
+/// A `StableIndexMap` is a `RegistOrderMap` that also gives every entry a `StableIndex`,
+/// for callers who keep data elsewhere keyed by entry and can't rely on positions,
+/// which shift when earlier entries are removed.
+#[derive(Clone)]
+pub struct StableIndexMap<K, V> {
+    map: RegistOrderMap<K, (StableIndex, V)>,
+    next: u64,
+}
+
+impl<K, V> StableIndexMap<K, V> {
+    /// Creates an empty StableIndexMap.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Inserts a key-value pair and returns the stable index of its entry.
+    /// A new key is appended with a fresh index; an existing key keeps its index and position.
+    pub fn insert(&mut self, k: K, v: V) -> StableIndex
+    where
+        K: Eq,
+    {
+        match self.map.entry(k) {
+            crate::Entry::Occupied(mut entry) => {
+                let index = entry.get().0;
+                entry.get_mut().1 = v;
+                index
+            }
+            crate::Entry::Vacant(entry) => {
+                let index = StableIndex(self.next);
+                self.next += 1;
+                entry.insert((index, v));
+                index
+            }
+        }
+    }
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.map.get(k).map(|(_, v)| v)
+    }
+    /// Returns the stable index of the key's entry, if it is present.
+    pub fn get_stable_index<Q>(&self, k: &Q) -> Option<StableIndex>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.map.get(k).map(|(index, _)| *index)
+    }
+    /// Returns the key-value pair with the given stable index,
+    /// or `None` if its entry has been removed.
+    pub fn get_by_stable_index(&self, index: StableIndex) -> Option<(&K, &V)> {
+        self.map
+            .entries
+            .iter()
+            .find(|e| e.val.0 == index)
+            .map(|e| (&e.key, &e.val.1))
+    }
+    /// Removes a key from the map, keeping the order of the rest, and returns its value.
+    /// The stable indices of the other entries are unaffected.
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.map.remove(k).map(|(_, v)| v)
+    }
+    /// Returns the current position of the entry with the given stable index.
+    pub fn position(&self, index: StableIndex) -> Option<usize> {
+        self.map.entries.iter().position(|e| e.val.0 == index)
+    }
+    /// An iterator visiting all key-value pairs in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map.entries.iter().map(|e| (&e.key, &e.val.1))
+    }
+    /// Returns the number of elements in the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+    /// Returns true if the map contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl<K, V> Default for StableIndexMap<K, V> {
+    fn default() -> Self {
+        Self {
+            map: RegistOrderMap::new(),
+            next: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_index_survives_removal() {
+        let mut map = StableIndexMap::new();
+        let a = map.insert("a", 1);
+        let b = map.insert("b", 2);
+        let c = map.insert("c", 3);
+        assert_eq!(map.position(c), Some(2));
+        assert_eq!(map.remove(&"a"), Some(1));
+        assert_eq!(map.get_by_stable_index(b), Some((&"b", &2)));
+        assert_eq!(map.get_by_stable_index(c), Some((&"c", &3)));
+        assert_eq!(map.position(c), Some(1));
+        assert_eq!(map.get_stable_index(&"c"), Some(c));
+        assert_eq!(map.get_by_stable_index(a), None);
+        assert_eq!(map.position(a), None);
+
+        // Indices are never reused, even for a key that comes back.
+        let a2 = map.insert("a", 4);
+        assert_ne!(a2, a);
+        assert_eq!(map.get_by_stable_index(a), None);
+        assert_eq!(map.get_by_stable_index(a2), Some((&"a", &4)));
+        let pairs: Vec<_> = map.iter().collect();
+        assert_eq!(pairs, [(&"b", &2), (&"c", &3), (&"a", &4)]);
+    }
+
+    #[test]
+    fn test_insert_existing_keeps_index() {
+        let mut map = StableIndexMap::new();
+        let a = map.insert("a", 1);
+        map.insert("b", 2);
+        assert_eq!(map.insert("a", 10), a);
+        assert_eq!(map.get(&"a"), Some(&10));
+        assert_eq!(map.position(a), Some(0));
+        assert_eq!(map.len(), 2);
+        assert!(!map.is_empty());
+    }
+}
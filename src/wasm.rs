@@ -0,0 +1,93 @@
+use crate::RegistOrderMap;
+use js_sys::{Array, Map, Object, Reflect};
+use wasm_bindgen::{JsCast, JsValue};
+
+impl RegistOrderMap<String, JsValue> {
+    /// Converts the map into a JS `Object`, defining the properties in registration order.
+    ///
+    /// JS objects keep the insertion order of string keys, except for integer-like keys
+    /// such as `"1"`, which always come first in ascending order. Use `to_js_map` if the
+    /// keys may look like integers.
+    pub fn to_js_object(&self) -> Result<Object, JsValue> {
+        let object = Object::new();
+        for (k, v) in self.iter() {
+            Reflect::set(&object, &JsValue::from_str(k), v)?;
+        }
+        Ok(object)
+    }
+    /// Creates a map from the own enumerable string-keyed properties of a JS `Object`,
+    /// in the order returned by `Object.entries`.
+    pub fn from_js_object(object: &Object) -> Result<Self, JsValue> {
+        let entries = Object::entries(object);
+        let mut map = RegistOrderMap::with_capacity(entries.length() as usize);
+        for entry in entries.iter() {
+            let entry: Array = entry.dyn_into()?;
+            let key = entry
+                .get(0)
+                .as_string()
+                .ok_or_else(|| JsValue::from_str("object key is not a string"))?;
+            map.insert(key, entry.get(1));
+        }
+        Ok(map)
+    }
+    /// Converts the map into a JS `Map`, which keeps the registration order for every key.
+    pub fn to_js_map(&self) -> Map {
+        let js_map = Map::new();
+        for (k, v) in self.iter() {
+            js_map.set(&JsValue::from_str(k), v);
+        }
+        js_map
+    }
+    /// Creates a map from a JS `Map` in its insertion order.
+    /// Returns the offending key as the error if a key is not a string.
+    pub fn from_js_map(js_map: &Map) -> Result<Self, JsValue> {
+        let mut map = RegistOrderMap::with_capacity(js_map.size() as usize);
+        let mut error = None;
+        js_map.for_each(&mut |v, k| match k.as_string() {
+            Some(k) => map.insert(k, v),
+            None => {
+                error.get_or_insert(k);
+            }
+        });
+        match error {
+            Some(k) => Err(k),
+            None => Ok(map),
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn ordered_map() -> RegistOrderMap<String, JsValue> {
+        let mut map = RegistOrderMap::new();
+        map.insert("key2".to_string(), JsValue::from(20));
+        map.insert("key1".to_string(), JsValue::from(10));
+        map.insert("key3".to_string(), JsValue::from_str("30"));
+        map
+    }
+
+    fn keys(map: &RegistOrderMap<String, JsValue>) -> Vec<&str> {
+        map.iter().map(|(k, _)| k.as_str()).collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_js_object_round_trip() {
+        let object = ordered_map().to_js_object().unwrap();
+        let map = RegistOrderMap::from_js_object(&object).unwrap();
+        assert_eq!(keys(&map), ["key2", "key1", "key3"]);
+        assert_eq!(map.get("key1").unwrap().as_f64(), Some(10.0));
+        assert_eq!(map.get("key3").unwrap().as_string().as_deref(), Some("30"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_js_map_round_trip() {
+        let js_map = ordered_map().to_js_map();
+        assert_eq!(js_map.size(), 3);
+        let map = RegistOrderMap::from_js_map(&js_map).unwrap();
+        assert_eq!(keys(&map), ["key2", "key1", "key3"]);
+        assert_eq!(map.get("key2").unwrap().as_f64(), Some(20.0));
+    }
+}